@@ -85,10 +85,9 @@ pub struct TodoDatabase {
 impl TodoDatabase {
     pub fn new(rt: &Runtime) -> Self {
         let conn = Connection::open_in_memory().expect("should open connection");
-        let env = PowerSyncEnvironment::custom(
+        let env = PowerSyncEnvironment::with_tokio(
             reqwest::Client::new(),
             ConnectionPool::single_connection(conn),
-            PowerSyncEnvironment::tokio_timer(),
         );
         let mut schema = Schema::default();
         schema.tables.push(TodoList::schema());
@@ -120,6 +119,7 @@ impl TodoDatabase {
         Ok(PowerSyncCredentials {
             endpoint: "http://localhost:8080".to_string(),
             token: token.token,
+            expires_at: None,
         })
     }
 }
@@ -150,7 +150,11 @@ impl<'a> TodoAppContent<'a> {
             let ctx = self.ctx.clone();
 
             async move {
-                let stream = state.db.db.sync_stream("todos", Some(&stream_params));
+                let stream = state
+                    .db
+                    .db
+                    .sync_stream("todos", Some(&stream_params))
+                    .unwrap();
                 let _subscription = stream.subscribe().await.unwrap();
 
                 let mut stream = state.db.db.watch_tables(true, ["todos"]);
@@ -182,7 +186,7 @@ impl<'a> TodoAppContent<'a> {
     }
 
     fn build_home_page(&mut self, ui: &mut Ui) {
-        let lists_stream = self.app.shared.db.db.sync_stream("lists", None);
+        let lists_stream = self.app.shared.db.db.sync_stream("lists", None).unwrap();
         let has_lists = {
             let lists_status = self.status.for_stream(&lists_stream);
             match lists_status {
@@ -214,7 +218,8 @@ impl<'a> TodoAppContent<'a> {
             .shared
             .db
             .db
-            .sync_stream("todos", Some(&state.stream_params));
+            .sync_stream("todos", Some(&state.stream_params))
+            .unwrap();
         let did_sync = {
             let lists_status = self.status.for_stream(&stream);
             match lists_status {
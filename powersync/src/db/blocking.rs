@@ -0,0 +1,38 @@
+use futures_lite::future;
+
+use crate::{
+    CrudTransaction, PowerSyncDatabase, SyncOptions, db::pool::LeasedConnection,
+    error::PowerSyncError,
+};
+
+/// Blocking variants of async [PowerSyncDatabase] methods, for CLI tools and scripts that don't
+/// use an async runtime.
+///
+/// These methods block the current thread until the operation completes. They must not be called
+/// from within an async context, as that would block the executor.
+impl PowerSyncDatabase {
+    /// Blocking variant of [Self::reader], see [Self::reader_blocking].
+    pub fn blocking_reader(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.reader_blocking()
+    }
+
+    /// Blocking variant of [Self::writer], see [Self::writer_blocking].
+    pub fn blocking_writer(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.writer_blocking()
+    }
+
+    /// Blocking variant of [Self::connect].
+    ///
+    /// The download and upload actors still need to be polled, so they should be spawned on
+    /// another thread before calling this.
+    pub fn blocking_connect(&self, options: SyncOptions) {
+        future::block_on(self.connect(options))
+    }
+
+    /// Blocking variant of [Self::next_crud_transaction].
+    pub fn blocking_next_crud_transaction<'a>(
+        &'a self,
+    ) -> Result<Option<CrudTransaction<'a>>, PowerSyncError> {
+        future::block_on(self.next_crud_transaction())
+    }
+}
@@ -1,6 +1,6 @@
 use crate::error::{PowerSyncError, RawPowerSyncError};
 use num_traits::cast::FromPrimitive;
-use powersync_sqlite_nostd::bindings::sqlite3_open_v2;
+use powersync_sqlite_nostd::bindings::{sqlite3_limit, sqlite3_open_v2};
 use powersync_sqlite_nostd::{Connection, ManagedConnection, ManagedStmt, ResultCode, sqlite3};
 use std::ffi::{CStr, CString, c_int};
 use std::mem::MaybeUninit;
@@ -59,6 +59,28 @@ impl SqliteConnection {
         Ok(())
     }
 
+    /// Returns whether the connection is in autocommit mode, meaning that no transaction is
+    /// active.
+    pub fn is_autocommit(&self) -> bool {
+        unsafe {
+            // Safety: Only queries the state of the connection.
+            self.handle().get_autocommit()
+        }
+    }
+
+    /// Returns the maximum amount of columns in a table or view supported by this connection.
+    ///
+    /// This depends on the `SQLITE_MAX_COLUMN` compile-time option and runtime limits.
+    pub fn column_limit(&self) -> usize {
+        const SQLITE_LIMIT_COLUMN: c_int = 2;
+
+        let limit = unsafe {
+            // Safety: Passing a negative value only queries the limit.
+            sqlite3_limit(self.handle(), SQLITE_LIMIT_COLUMN, -1)
+        };
+        limit as usize
+    }
+
     pub fn prepare(&self, stmt: &str) -> Result<ManagedStmt, PowerSyncError> {
         unsafe {
             // Safety: We're not doing anything that could close the connection.
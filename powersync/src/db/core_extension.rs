@@ -1,51 +1,123 @@
 use crate::db::connection::SqliteConnection;
 use crate::error::{PowerSyncError, RawPowerSyncError};
+use log::warn;
 use powersync_sqlite_nostd::ResultCode;
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, ops::Range, str::FromStr};
 
-#[derive(Clone, PartialEq, PartialOrd, Eq, Ord)]
+/// The version of the PowerSync core extension, as reported by `powersync_rs_version()`.
+///
+/// Versions are compared by their `major`, `minor` and `patch` components only, the
+/// [Self::build_hash] is ignored.
+#[derive(Clone, Debug)]
 pub struct CoreExtensionVersion {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
+    /// The git commit the core extension was built from, if reported.
+    pub build_hash: Option<String>,
 }
 
 impl CoreExtensionVersion {
     /// The minimum version of the core extension supported by the native SDK.
     pub const MINIMUM: Self = Self::new(0, 4, 7);
     pub const MAXIMUM_EXCLUSIVE: Self = Self::new(0, 5, 0);
+    /// The exclusive upper bound of versions that are expected to be compatible with this SDK
+    /// despite not being tested with it. These versions are accepted with a warning by default.
+    pub const COMPATIBLE_EXCLUSIVE: Self = Self::new(0, 6, 0);
 
     pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
         Self {
             major,
             minor,
             patch,
+            build_hash: None,
         }
     }
 
+    /// The range of core extension versions supported by this SDK version.
+    pub const fn supported() -> Range<Self> {
+        Self::MINIMUM..Self::MAXIMUM_EXCLUSIVE
+    }
+
+    /// The range of core extension versions accepted by default, which includes newer versions
+    /// within a known-compatible minor release.
+    pub const fn compatible() -> Range<Self> {
+        Self::MINIMUM..Self::COMPATIBLE_EXCLUSIVE
+    }
+
+    /// Validates that this version is in the [Self::compatible] range, see [Self::validate_in].
     pub fn validate(&self) -> Result<(), PowerSyncError> {
-        if self < &Self::MINIMUM || self >= &Self::MAXIMUM_EXCLUSIVE {
-            Err(RawPowerSyncError::InvalidCoreExtensionVersion {
-                actual: format!("Expected version ^{}, got {}", Self::MINIMUM, self),
+        self.validate_in(&Self::compatible())
+    }
+
+    /// Validates that this version is in the `accepted` range, which can be configured with
+    /// [crate::env::PowerSyncEnvironment::with_core_extension_versions].
+    ///
+    /// Versions that are accepted despite not being in the [Self::supported] range log a warning.
+    pub fn validate_in(&self, accepted: &Range<Self>) -> Result<(), PowerSyncError> {
+        if !accepted.contains(self) {
+            return Err(RawPowerSyncError::InvalidCoreExtensionVersion {
+                actual: format!(
+                    "Expected version >={}, <{}, got {}",
+                    accepted.start, accepted.end, self
+                ),
             }
-            .into())
-        } else {
-            Ok(())
+            .into());
+        }
+
+        if !Self::supported().contains(self) {
+            warn!(
+                "PowerSync core extension version {self} is outside of the supported range ^{}",
+                Self::MINIMUM
+            );
         }
+
+        Ok(())
+    }
+
+    fn components(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch)
     }
 
-    pub(crate) fn check_from_db(conn: &SqliteConnection) -> Result<Self, PowerSyncError> {
+    pub(crate) fn from_db(conn: &SqliteConnection) -> Result<Self, PowerSyncError> {
         let stmt = conn.prepare("SELECT powersync_rs_version()")?;
         let ResultCode::ROW = stmt.step()? else {
             panic!("Expected row") // Can't happen, scalar select
         };
 
-        let version = stmt.column_text(0)?.parse::<Self>()?;
-        version.validate()?;
+        stmt.column_text(0)?.parse::<Self>()
+    }
+
+    pub(crate) fn check_from_db(
+        conn: &SqliteConnection,
+        accepted: &Range<Self>,
+    ) -> Result<Self, PowerSyncError> {
+        let version = Self::from_db(conn)?;
+        version.validate_in(accepted)?;
         Ok(version)
     }
 }
 
+impl PartialEq for CoreExtensionVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.components() == other.components()
+    }
+}
+
+impl Eq for CoreExtensionVersion {}
+
+impl PartialOrd for CoreExtensionVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CoreExtensionVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.components().cmp(&other.components())
+    }
+}
+
 impl FromStr for CoreExtensionVersion {
     type Err = PowerSyncError;
 
@@ -69,13 +141,80 @@ impl FromStr for CoreExtensionVersion {
         let major = next_component()?;
         let minor = next_component()?;
         let patch = next_component()?;
+        let build_hash = s
+            .split_once('/')
+            .map(|(_, hash)| hash.to_string())
+            .filter(|hash| !hash.is_empty());
 
-        Ok(Self::new(major, minor, patch))
+        Ok(Self {
+            build_hash,
+            ..Self::new(major, minor, patch)
+        })
     }
 }
 
 impl Display for CoreExtensionVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(hash) = &self.build_hash {
+            write!(f, "/{hash}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CoreExtensionVersion;
+
+    #[test]
+    fn parse_with_build_hash() {
+        let version: CoreExtensionVersion = "0.4.7/abcdef".parse().unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (0, 4, 7));
+        assert_eq!(version.build_hash.as_deref(), Some("abcdef"));
+        assert_eq!(version.to_string(), "0.4.7/abcdef");
+        version.validate().unwrap();
+    }
+
+    #[test]
+    fn compare_ignores_build_hash() {
+        let version: CoreExtensionVersion = "0.4.7/abc".parse().unwrap();
+        assert_eq!(version, CoreExtensionVersion::new(0, 4, 7));
+        assert_eq!(version, "0.4.7/def".parse().unwrap());
+        assert!(version < CoreExtensionVersion::new(0, 4, 8));
+        assert!(!(CoreExtensionVersion::new(0, 4, 0)..version.clone()).contains(&version));
+    }
+
+    #[test]
+    fn validate_compatible_version() {
+        // Newer versions in a known-compatible minor release are accepted with a warning.
+        let version = CoreExtensionVersion::new(0, 5, 1);
+        assert!(!CoreExtensionVersion::supported().contains(&version));
+        version.validate().unwrap();
+
+        assert!(CoreExtensionVersion::new(0, 6, 0).validate().is_err());
+        assert!(CoreExtensionVersion::new(0, 4, 6).validate().is_err());
+    }
+
+    #[test]
+    fn validate_in_widened_range() {
+        let version = CoreExtensionVersion::new(0, 6, 1);
+        assert!(version.validate().is_err());
+
+        let widened = CoreExtensionVersion::MINIMUM..CoreExtensionVersion::new(0, 7, 0);
+        version.validate_in(&widened).unwrap();
+        assert!(
+            CoreExtensionVersion::new(0, 7, 0)
+                .validate_in(&widened)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_without_build_hash() {
+        let version: CoreExtensionVersion = "0.4.10".parse().unwrap();
+        assert_eq!(version, CoreExtensionVersion::new(0, 4, 10));
+        assert!("0.4".parse::<CoreExtensionVersion>().is_err());
     }
 }
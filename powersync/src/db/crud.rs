@@ -1,10 +1,11 @@
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures_lite::{FutureExt, Stream, ready};
+use futures_lite::{FutureExt, Stream, StreamExt, ready, stream};
 use pin_project_lite::pin_project;
 use powersync_sqlite_nostd::ResultCode;
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_json::{Map, Value};
 
 use crate::PowerSyncDatabase;
@@ -77,22 +78,39 @@ pub struct CrudEntry {
     /// This is only tracked for tables for which this has been enabled by setting
     /// the [Table::track_previous_values].
     pub previous_values: Option<Map<String, Value>>,
+    /// The JSON source [Self::data] has been parsed from.
+    ///
+    /// This can be used to forward data to a backend without serializing it again.
+    pub raw_data: Option<Box<str>>,
+    /// The JSON source [Self::previous_values] has been parsed from.
+    pub raw_previous_values: Option<Box<str>>,
 }
 
 impl CrudEntry {
-    fn parse(id: i64, tx_id: i64, data: &str) -> Result<Self, PowerSyncError> {
+    pub(crate) fn parse(id: i64, tx_id: i64, data: &str) -> Result<Self, PowerSyncError> {
         #[derive(Deserialize)]
         struct CrudData {
             op: UpdateType,
             #[serde(rename = "type")]
             table: String,
             id: String,
-            data: Option<Map<String, Value>>,
+            data: Option<Box<RawValue>>,
             metadata: Option<String>,
-            old: Option<Map<String, Value>>,
+            old: Option<Box<RawValue>>,
+        }
+
+        fn parse_object(
+            raw: &Option<Box<RawValue>>,
+        ) -> Result<Option<Map<String, Value>>, PowerSyncError> {
+            Ok(match raw {
+                Some(raw) => serde_json::from_str(raw.get())?,
+                None => None,
+            })
         }
 
         let data: CrudData = serde_json::from_str(data)?;
+        let parsed_data = parse_object(&data.data)?;
+        let parsed_old = parse_object(&data.old)?;
 
         Ok(Self {
             client_id: id,
@@ -101,12 +119,27 @@ impl CrudEntry {
             table: data.table,
             id: data.id,
             metadata: data.metadata,
-            data: data.data,
-            previous_values: data.old,
+            data: parsed_data,
+            previous_values: parsed_old,
+            raw_data: data.data.map(Into::into),
+            raw_previous_values: data.old.map(Into::into),
         })
     }
 }
 
+/// The outcome of a write made through [PowerSyncDatabase::execute_tracked].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecResult {
+    /// The number of rows changed by the statement, as reported by SQLite.
+    pub rows_affected: usize,
+    /// Whether the statement added entries to the CRUD upload queue.
+    ///
+    /// This can be false even if rows were affected, e.g. for writes on local-only tables or for
+    /// no-op updates on tables with [crate::schema::TableOptions::ignore_empty_updates]
+    /// enabled.
+    pub crud_generated: bool,
+}
+
 /// Type of local change.
 #[derive(Deserialize, Serialize)]
 pub enum UpdateType {
@@ -121,6 +154,56 @@ pub enum UpdateType {
     Delete,
 }
 
+/// Returns a [Stream] of all pending [CrudEntry] items in the order in which they were made,
+/// without grouping them by transaction.
+pub(crate) fn crud_entries(
+    db: &PowerSyncDatabase,
+) -> impl Stream<Item = Result<CrudEntry, PowerSyncError>> + '_ {
+    stream::unfold(Some(-1), move |last| async move {
+        let last = last?;
+
+        match next_crud_entries(db, last).await {
+            Ok(entries) => {
+                let next = entries.last()?.client_id;
+                Some((Ok(entries), Some(next)))
+            }
+            // Stop after emitting the error.
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+    .flat_map(|batch| {
+        stream::iter(match batch {
+            Ok(entries) => entries.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        })
+    })
+}
+
+async fn next_crud_entries(
+    db: &PowerSyncDatabase,
+    last: i64,
+) -> Result<Vec<CrudEntry>, PowerSyncError> {
+    const BATCH_SIZE: i64 = 100;
+
+    let reader = db.reader().await?;
+    let conn = reader.sqlite_connection();
+    let stmt =
+        conn.prepare("SELECT id, tx_id, data FROM ps_crud WHERE id > ? ORDER BY id LIMIT ?")?;
+    stmt.bind_int64(1, last)?;
+    stmt.bind_int64(2, BATCH_SIZE)?;
+
+    let mut entries = vec![];
+    while let ResultCode::ROW = stmt.step()? {
+        let id = stmt.column_int64(0);
+        let tx_id = stmt.column_int64(1);
+        let data = stmt.column_text(2)?;
+
+        entries.push(CrudEntry::parse(id, tx_id, data)?);
+    }
+
+    Ok(entries)
+}
+
 type Boxed<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 pin_project! {
@@ -1,6 +1,7 @@
 use crate::db::connection::{SqliteConnection, TransactionGuard, exec_stmt};
 use crate::schema::SchemaOrCustom;
 use crate::{
+    RetryPolicy, SyncOptions,
     db::{
         core_extension::CoreExtensionVersion, pool::LeasedConnection, streams::SyncStreamTracker,
     },
@@ -10,10 +11,13 @@ use crate::{
     util::SharedFuture,
 };
 use event_listener::EventListener;
-use futures_lite::{FutureExt, Stream, StreamExt, ready};
+use futures_lite::{FutureExt, Stream, StreamExt, future, ready};
+use log::{info, warn};
 use powersync_sqlite_nostd::{Destructor, ResultCode};
-use std::sync::{Mutex, Weak};
-use std::time::Duration;
+use std::sync::{
+    Mutex, Weak,
+    atomic::{AtomicBool, Ordering},
+};
 use std::{
     pin::Pin,
     sync::Arc,
@@ -37,10 +41,19 @@ pub struct InnerPowerSyncState {
     /// reference to [InnerPowerSyncState], we only keep a weak reference here to ensure we can drop
     /// actors through the channels owned by [SyncCoordinator].
     pub(crate) sync: Weak<SyncCoordinator>,
-    pub(crate) retry_delay: Mutex<Option<Duration>>,
+    pub(crate) retry_policy: Mutex<Option<RetryPolicy>>,
+    /// The options passed to the last `connect()` call, used for requests outside of sync
+    /// iterations.
+    pub(crate) last_options: Mutex<Option<SyncOptions>>,
+    /// Whether the schema applied during initialization differs from the one applied when the
+    /// database was last opened.
+    schema_changed: AtomicBool,
 }
 
 impl InnerPowerSyncState {
+    /// The key in `ps_kv` storing a hash of the last schema applied to the database.
+    const SCHEMA_HASH_KEY: &'static str = "sdk_schema_hash";
+
     pub fn new(
         env: PowerSyncEnvironment,
         schema: SchemaOrCustom,
@@ -52,8 +65,10 @@ impl InnerPowerSyncState {
             schema: Arc::new(schema),
             status: SyncStatus::new(),
             current_streams: SyncStreamTracker::default(),
-            retry_delay: Default::default(),
+            retry_policy: Default::default(),
+            last_options: Default::default(),
             sync: Arc::downgrade(sync),
+            schema_changed: AtomicBool::new(false),
         }
     }
 
@@ -61,14 +76,15 @@ impl InnerPowerSyncState {
         let pool = &self.env.pool;
         self.did_initialize
             .run(|| async {
-                let conn = pool.writer().await;
+                let conn = pool.try_writer().await?;
                 let conn = conn.sqlite_connection();
-                CoreExtensionVersion::check_from_db(conn)?;
+                CoreExtensionVersion::check_from_db(conn, &self.env.core_extension_versions)?;
 
                 conn.exec(c"SELECT powersync_init()")?;
 
                 self.update_schema_internal(conn)?;
-                self.status.update(|old| old.resolve_offline_state(conn))?;
+                self.status
+                    .update(|old| old.resolve_offline_state(conn, self.env.clock))?;
 
                 Ok(())
             })
@@ -78,7 +94,7 @@ impl InnerPowerSyncState {
 
     fn update_schema_internal(&self, conn: &SqliteConnection) -> Result<(), PowerSyncError> {
         if let SchemaOrCustom::Schema(schema) = self.schema.as_ref() {
-            schema.validate()?;
+            schema.validate_with_column_limit(conn.column_limit())?;
         };
 
         let serialized_schema = serde_json::to_string(&self.schema)?;
@@ -87,6 +103,26 @@ impl InnerPowerSyncState {
         stmt.bind_text(1, &serialized_schema, Destructor::STATIC)?;
         exec_stmt(stmt)?;
 
+        let schema_hash = format!("{:016x}", fnv1a_hash(serialized_schema.as_bytes()));
+        let previous_hash = {
+            let stmt = conn.prepare("SELECT value FROM ps_kv WHERE key = ?")?;
+            stmt.bind_text(1, Self::SCHEMA_HASH_KEY, Destructor::STATIC)?;
+            match stmt.step()? {
+                ResultCode::ROW => Some(stmt.column_text(0)?.to_string()),
+                _ => None,
+            }
+        };
+
+        if previous_hash.as_ref() != Some(&schema_hash) {
+            info!("Schema has changed since the database was last opened");
+            self.schema_changed.store(true, Ordering::SeqCst);
+
+            let stmt = conn.prepare("INSERT OR REPLACE INTO ps_kv (key, value) VALUES (?, ?)")?;
+            stmt.bind_text(1, Self::SCHEMA_HASH_KEY, Destructor::STATIC)?;
+            stmt.bind_text(2, &schema_hash, Destructor::STATIC)?;
+            exec_stmt(stmt)?;
+        }
+
         // TODO: Update readers? Should be fine at the moment because we're only doing this during
         // initialization.
         Ok(())
@@ -112,15 +148,35 @@ impl InnerPowerSyncState {
         if let Some(write_checkpoint) = write_checkpoint {
             // If there are no remaining crud items we can set the target op to the checkpoint.
             let stmt = writer.inner.prepare("SELECT 1 FROM ps_crud LIMIT 1")?;
-            if let ResultCode::OK = stmt.step()? {
+            if let ResultCode::DONE = stmt.step()? {
                 target_op = write_checkpoint;
             }
         }
 
+        // Don't let stale checkpoints rewind the target op.
+        if target_op != MAX_OP_ID
+            && let Some(current) = Self::local_target_op(writer.inner)?
+            && current != MAX_OP_ID
+            && target_op < current
+        {
+            warn!("Ignoring write checkpoint {target_op}, which is older than {current}");
+            target_op = current;
+        }
+
         Self::set_local_target_op(writer.inner, target_op)?;
         writer.commit()
     }
 
+    fn local_target_op(writer: &SqliteConnection) -> Result<Option<i64>, PowerSyncError> {
+        let stmt = writer.prepare("SELECT target_op FROM ps_buckets WHERE name = ?")?;
+        stmt.bind_text(1, "$local", Destructor::STATIC)?;
+
+        Ok(match stmt.step()? {
+            ResultCode::ROW => Some(stmt.column_int64(0)),
+            _ => None,
+        })
+    }
+
     pub fn set_local_target_op(writer: &SqliteConnection, op: i64) -> Result<(), PowerSyncError> {
         let stmt = writer.prepare("UPDATE ps_buckets SET target_op = ? WHERE name = ?")?;
         stmt.bind_int64(1, op)?;
@@ -128,20 +184,78 @@ impl InnerPowerSyncState {
         exec_stmt(stmt)
     }
 
+    /// Whether the schema differs from the one used when the database was last opened.
+    pub async fn schema_changed(&self) -> Result<bool, PowerSyncError> {
+        self.initialize().await?;
+        Ok(self.schema_changed.load(Ordering::SeqCst))
+    }
+
+    /// Returns the stable id of this client, used when requesting write checkpoints.
+    pub async fn client_id(&self) -> Result<String, PowerSyncError> {
+        let reader = self.reader().await?;
+        let stmt = reader
+            .sqlite_connection()
+            .prepare("SELECT powersync_client_id()")?;
+        let ResultCode::ROW = stmt.step()? else {
+            panic!("Expected row"); // Can't happen, scalar select
+        };
+
+        Ok(stmt.column_text(0)?.to_string())
+    }
+
+    /// Generates a random id attached to requests to the PowerSync service, so that client and
+    /// service logs can be correlated.
+    ///
+    /// The id is formatted as a version 4 UUID, using the [crate::env::Random] source of the
+    /// environment. This doesn't need a database connection, so it can't block on leases.
+    pub fn generate_request_id(&self) -> String {
+        let high = self.env.random.next_u64();
+        let low = self.env.random.next_u64();
+
+        // Set the version (4) and variant (RFC 4122) bits.
+        let high = (high & !0xf000) | 0x4000;
+        let low = (low & !(0b11 << 62)) | (0b10 << 62);
+
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            high >> 32,
+            (high >> 16) & 0xffff,
+            high & 0xffff,
+            low >> 48,
+            low & 0xffff_ffff_ffff
+        )
+    }
+
     pub async fn reader(&self) -> Result<LeasedConnection, PowerSyncError> {
         self.initialize().await?;
-        Ok(self.env.pool.reader().await)
+        self.env.pool.try_reader().await
+    }
+
+    pub async fn readers(&self, count: usize) -> Result<Vec<LeasedConnection>, PowerSyncError> {
+        self.initialize().await?;
+        self.env.pool.try_readers(count).await
     }
 
     pub async fn writer(&self) -> Result<LeasedConnection, PowerSyncError> {
         self.initialize().await?;
-        Ok(self.env.pool.writer().await)
+        self.env.pool.try_writer().await
     }
 
-    pub async fn sync_iteration_delay(&self) {
+    pub fn reader_blocking(&self) -> Result<LeasedConnection, PowerSyncError> {
+        future::block_on(self.initialize())?;
+        self.env.pool.try_reader_sync()
+    }
+
+    pub fn writer_blocking(&self) -> Result<LeasedConnection, PowerSyncError> {
+        future::block_on(self.initialize())?;
+        self.env.pool.try_writer_sync()
+    }
+
+    /// Waits for the delay configured by the [RetryPolicy] after `attempt` consecutive failures.
+    pub async fn sync_iteration_delay(&self, attempt: usize) {
         let delay = {
-            let guard = self.retry_delay.lock().unwrap();
-            *guard
+            let guard = self.retry_policy.lock().unwrap();
+            guard.map(|policy| policy.delay(attempt, self.env.random))
         };
 
         if let Some(delay) = delay {
@@ -212,3 +326,14 @@ impl InnerPowerSyncState {
         }
     }
 }
+
+/// A 64-bit FNV-1a hash, used because it's stable across Rust versions (unlike
+/// [std::hash::DefaultHasher]).
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
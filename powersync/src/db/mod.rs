@@ -1,25 +1,41 @@
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::db::async_support::AsyncDatabaseTasks;
-use crate::db::watch::ListenerConfiguration;
+use crate::db::watch::{ListenerConfiguration, TableChangeListener};
 use crate::schema::SchemaOrCustom;
-use crate::sync::coordinator::SyncCoordinator;
+use crate::sync::coordinator::{ActorStoppedGuard, SyncCoordinator};
 use crate::{
-    CrudTransaction, SyncOptions,
+    CrudEntry, CrudTransaction, StreamPriority, SyncOptions,
     db::{
-        crud::CrudTransactionStream, internal::InnerPowerSyncState, pool::LeasedConnection,
-        streams::SyncStream,
+        core_extension::CoreExtensionVersion,
+        crud::CrudTransactionStream,
+        internal::InnerPowerSyncState,
+        pool::LeasedConnection,
+        streams::{StreamSubscription, StreamSubscriptionOptions, SyncStream},
     },
     env::PowerSyncEnvironment,
-    error::PowerSyncError,
-    sync::{download::DownloadActor, status::SyncStatusData, upload::UploadActor},
+    error::{PowerSyncError, RawPowerSyncError},
+    sync::{
+        download::{
+            DownloadActor,
+            http::{test_sync_stream, write_checkpoint},
+        },
+        status::{ConnectionState, StatusChangeListener, SyncStatusData},
+        streams::{StreamDescription, StreamKey},
+        upload::UploadActor,
+    },
 };
-use futures_lite::{FutureExt, Stream, StreamExt};
+use futures_lite::{FutureExt, Stream, StreamExt, future};
+use powersync_sqlite_nostd::ResultCode;
 
 mod async_support;
+#[cfg(feature = "blocking")]
+mod blocking;
 pub(crate) mod connection;
 pub mod core_extension;
 pub mod crud;
@@ -27,6 +43,8 @@ pub(crate) mod internal;
 pub mod pool;
 pub mod schema;
 pub mod streams;
+#[cfg(feature = "uuid")]
+mod uuid;
 pub mod watch;
 
 #[derive(Clone)]
@@ -74,10 +92,21 @@ impl PowerSyncDatabase {
     pub fn async_tasks(&self) -> AsyncDatabaseTasks {
         let mut downloads = DownloadActor::new(self.inner.clone(), &self.sync);
         let mut uploads = UploadActor::new(self.inner.clone(), &self.sync);
+        // The guards only keep a weak reference, dropping the coordinator is what stops actors.
+        let download_stopped = ActorStoppedGuard::new(&self.sync);
+        let upload_stopped = ActorStoppedGuard::new(&self.sync);
 
         AsyncDatabaseTasks::new(
-            async move { downloads.run().await }.boxed(),
-            async move { uploads.run().await }.boxed(),
+            async move {
+                let _stopped = download_stopped;
+                downloads.run().await;
+            }
+            .boxed(),
+            async move {
+                let _stopped = upload_stopped;
+                uploads.run().await;
+            }
+            .boxed(),
         )
     }
 
@@ -87,6 +116,64 @@ impl PowerSyncDatabase {
         self.sync.connect(options, &self.inner).await
     }
 
+    /// Connects to the PowerSync service and waits for the first sync to complete.
+    ///
+    /// With a `priority`, this completes once all streams with that priority (or a higher one)
+    /// have been synced. Otherwise, this waits for a complete sync across all priorities.
+    /// If the sync client is disconnected or gives up retrying (see [crate::RetryPolicy]) before
+    /// that happens, an error is returned.
+    pub async fn connect_and_wait(
+        &self,
+        options: SyncOptions,
+        priority: Option<StreamPriority>,
+    ) -> Result<(), PowerSyncError> {
+        let priority = priority.unwrap_or(StreamPriority::SENTINEL);
+        let mut status = self.watch_status();
+        self.connect(options).await;
+
+        let mut has_started = false;
+        while let Some(data) = status.next().await {
+            if data.has_synced_priority(priority) {
+                return Ok(());
+            }
+            if let Some(e) = data.fatal_error() {
+                return Err(e.clone());
+            }
+
+            match data.connection_state() {
+                ConnectionState::Disconnected if has_started => break,
+                ConnectionState::Disconnected => {}
+                _ => has_started = true,
+            }
+        }
+
+        Err(RawPowerSyncError::DisconnectedBeforeSync.into())
+    }
+
+    /// Returns a future that completes once the sync client has completed a full sync after the
+    /// last call to [Self::connect].
+    ///
+    /// Unlike [StreamSubscription::wait_for_first_sync], this waits for a complete checkpoint
+    /// across all streams and priorities.
+    pub async fn wait_for_first_complete_sync(&self) {
+        self.inner
+            .wait_for_status(|status| status.did_complete_sync())
+            .await
+    }
+
+    /// Disconnects and stops the tasks returned by [Self::async_tasks], and then closes the
+    /// connection pool.
+    ///
+    /// This allows a deterministic shutdown, e.g. before exiting the process or deleting the
+    /// database file. Closing the pool waits for the writer connection to be returned, reader
+    /// connections that are still leased are closed once they're returned. After calling this
+    /// method, the database (and all its clones) can't be connected anymore, and leasing
+    /// connections fails with an error.
+    pub async fn close(&self) {
+        self.sync.close().await;
+        self.inner.env.pool.close().await;
+    }
+
     /// If the sync client is currently connected, requests it to disconnect.
     pub async fn disconnect(&self) {
         self.sync.disconnect().await
@@ -98,26 +185,16 @@ impl PowerSyncDatabase {
     /// The `emit_initially` option can be used to control whether the stream should emit as well
     /// when polled for the first time. This can be useful to build streams emitting a complete
     /// snapshot of results every time a source table is changed.
+    ///
+    /// This includes writes made by the sync client: Synced data is applied on the writer
+    /// connection, so the stream emits once data from a completed checkpoint can be queried.
     pub fn watch_tables<'a, Tables: IntoIterator<Item = impl Into<Cow<'a, str>>>>(
         &self,
         emit_initially: bool,
         tables: Tables,
     ) -> impl Stream<Item = ()> + 'static {
-        let config = ListenerConfiguration::if_matches(
-            tables
-                .into_iter()
-                .flat_map(|s| {
-                    let s = s.into();
-
-                    [
-                        format!("{}{s}", Self::PS_DATA_PREFIX),
-                        format!("{}{s}", Self::PS_DATA_LOCAL_PREFIX),
-                        Cow::into_owned(s),
-                    ]
-                })
-                .collect(),
-            emit_initially,
-        );
+        let config =
+            ListenerConfiguration::if_matches(Self::resolve_table_names(tables), emit_initially);
 
         self.inner
             .env
@@ -127,6 +204,69 @@ impl PowerSyncDatabase {
             .map(|_| ())
     }
 
+    /// Registers a `callback` that is invoked every time one of the specified tables is written to.
+    ///
+    /// This is an alternative to [Self::watch_tables] for code preferring callbacks over streams.
+    /// The callback is invoked synchronously on the thread returning the writer connection to the
+    /// pool, so it should return quickly and must not lease connections itself. It stays
+    /// registered until the returned [TableChangeListener] is dropped.
+    pub fn on_tables_changed<'a, Tables: IntoIterator<Item = impl Into<Cow<'a, str>>>>(
+        &self,
+        tables: Tables,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> TableChangeListener {
+        self.inner
+            .env
+            .pool
+            .update_notifiers()
+            .listen_callback(Self::resolve_table_names(tables), callback)
+    }
+
+    /// Resolves the names of tables that a user might want to watch to the names of the
+    /// underlying tables written to, covering both synced and local-only tables.
+    fn resolve_table_names<'a, Tables: IntoIterator<Item = impl Into<Cow<'a, str>>>>(
+        tables: Tables,
+    ) -> HashSet<String> {
+        tables
+            .into_iter()
+            .flat_map(|s| {
+                let s = s.into();
+
+                [
+                    format!("{}{s}", Self::PS_DATA_PREFIX),
+                    format!("{}{s}", Self::PS_DATA_LOCAL_PREFIX),
+                    Cow::into_owned(s),
+                ]
+            })
+            .collect()
+    }
+
+    /// Like [Self::watch_tables], but coalesces writes happening within `window` after an update
+    /// into a single event.
+    ///
+    /// This is useful for code issuing many small writes in a tight loop, where downstream
+    /// consumers would otherwise be notified for each of them. The delay is scheduled with the
+    /// [crate::env::Timer] of the environment used to open this database.
+    pub fn watch_tables_coalesced<'a, Tables: IntoIterator<Item = impl Into<Cow<'a, str>>>>(
+        &self,
+        emit_initially: bool,
+        tables: Tables,
+        window: Duration,
+    ) -> impl Stream<Item = ()> + 'static {
+        let timer = self.inner.env.timer;
+        let updates = self.watch_tables(emit_initially, tables).boxed();
+
+        futures_lite::stream::unfold(updates, move |mut updates| async move {
+            updates.next().await?;
+
+            // Give further writes a chance to arrive, then merge them into this event.
+            timer.delay_once(window).await;
+            while let Some(Some(())) = future::poll_once(updates.next()).await {}
+
+            Some(((), updates))
+        })
+    }
+
     /// Returns a stream emitting an item whenever any table in the local database is written to.
     pub fn watch_all_updates(&self) -> impl Stream<Item = HashSet<String>> + 'static {
         self.inner
@@ -187,6 +327,106 @@ impl PowerSyncDatabase {
         })
     }
 
+    /// Returns a [Stream] running the `sql` count query (e.g. `SELECT count(*) FROM ...`) initially
+    /// and every time one of the `tables` is written to.
+    ///
+    /// The query must return a single row with a single integer column.
+    #[cfg(feature = "rusqlite")]
+    pub fn watch_count<'a, P: rusqlite::Params + Clone + 'static>(
+        &self,
+        tables: impl IntoIterator<Item = impl Into<Cow<'a, str>>>,
+        sql: String,
+        params: P,
+    ) -> impl Stream<Item = Result<i64, PowerSyncError>> + 'static {
+        let db = self.clone();
+        self.watch_tables(true, tables).then(move |_| {
+            let db = db.clone();
+            let sql = sql.clone();
+            let params = params.clone();
+
+            async move {
+                db.query_one_cached(&sql, params, |row| row.get::<_, i64>(0))
+                    .await
+            }
+        })
+    }
+
+    /// Returns a [Stream] of query results paired with the current sync status, emitting whenever
+    /// one of the `tables` is written to or the status of the `stream` subscription changes.
+    ///
+    /// This packages a common pattern for offline-first lists, which need to be refreshed when
+    /// either their data or the sync status of the stream providing that data (e.g. its download
+    /// progress) changes. Use [SyncStatusData::for_stream] to inspect the status of the stream.
+    #[cfg(feature = "rusqlite")]
+    pub fn watch_list<'a, T: 'a, P, Tables: IntoIterator<Item = impl Into<Cow<'a, str>>>>(
+        &'a self,
+        tables: Tables,
+        stream: &StreamSubscription,
+        sql: String,
+        params: P,
+        map_row: impl Fn(&rusqlite::Row<'_>) -> rusqlite::Result<T> + 'a,
+    ) -> impl Stream<Item = Result<(Vec<T>, Arc<SyncStatusData>), PowerSyncError>> + 'a
+    where
+        P: rusqlite::Params + Clone + 'a,
+    {
+        enum Trigger {
+            Tables,
+            Status(Arc<SyncStatusData>),
+        }
+
+        // Only status changes affecting the stream should trigger a new query.
+        let key = StreamKey::from(StreamDescription::from(stream));
+        let stream_state = move |status: &SyncStatusData| {
+            let description = StreamDescription {
+                name: &key.name,
+                parameters: key.parameters.as_deref(),
+            };
+
+            status.for_stream(description).map(|stream| {
+                (
+                    stream.subscription.is_active(),
+                    stream.subscription.last_synced_at(),
+                    stream.progress.map(|p| (p.downloaded, p.total)),
+                )
+            })
+        };
+
+        let status = self.status();
+        let mut last_state = stream_state(&status);
+        let status_changes = self.watch_status().filter_map(move |status| {
+            let state = stream_state(&status);
+            if state == last_state {
+                None
+            } else {
+                last_state = state;
+                Some(Trigger::Status(status))
+            }
+        });
+        let table_changes = self.watch_tables(true, tables).map(|_| Trigger::Tables);
+        let triggers = Box::pin(futures_lite::stream::or(table_changes, status_changes));
+
+        futures_lite::stream::unfold(
+            (triggers, status, sql, params, map_row),
+            move |(mut triggers, mut status, sql, params, map_row)| async move {
+                if let Trigger::Status(new_status) = triggers.next().await? {
+                    status = new_status;
+                }
+
+                let rows = async {
+                    let reader = self.reader().await?;
+                    let mut stmt = reader.prepare_cached(&sql)?;
+                    let rows = stmt
+                        .query_map(params.clone(), &map_row)?
+                        .collect::<Result<Vec<T>, _>>()?;
+                    Ok::<_, PowerSyncError>((rows, status.clone()))
+                }
+                .await;
+
+                Some((rows, (triggers, status, sql, params, map_row)))
+            },
+        )
+    }
+
     #[cfg(feature = "rusqlite")]
     fn emit_on_statement_changes(
         &self,
@@ -256,6 +496,326 @@ impl PowerSyncDatabase {
             .collect())
     }
 
+    /// Runs a query on a reader connection, mapping its first row with `map_row`.
+    ///
+    /// Returns an error if the query doesn't return any rows, see [Self::query_opt] for a variant
+    /// that allows empty results.
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_one<T, P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+        map_row: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<T, PowerSyncError> {
+        let reader = self.reader().await?;
+        Ok(reader.query_row(sql, params, map_row)?)
+    }
+
+    /// Runs a query on a reader connection, mapping its first row with `map_row` or returning
+    /// [None] if the query didn't return any rows.
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_opt<T, P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+        map_row: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<Option<T>, PowerSyncError> {
+        use rusqlite::OptionalExtension;
+
+        let reader = self.reader().await?;
+        Ok(reader.query_row(sql, params, map_row).optional()?)
+    }
+
+    /// Like [Self::query_one], but prepares `sql` through the statement cache of the reader
+    /// connection.
+    ///
+    /// This avoids parsing the statement again for queries that run repeatedly. The size of the
+    /// cache can be configured with [crate::PoolOptions::with_statement_cache_capacity].
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_one_cached<T, P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+        map_row: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<T, PowerSyncError> {
+        let reader = self.reader().await?;
+        let mut stmt = reader.prepare_cached(sql)?;
+        Ok(stmt.query_row(params, map_row)?)
+    }
+
+    /// Like [Self::query_opt], but prepares `sql` through the statement cache of the reader
+    /// connection.
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_opt_cached<T, P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+        map_row: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<Option<T>, PowerSyncError> {
+        use rusqlite::OptionalExtension;
+
+        let reader = self.reader().await?;
+        let mut stmt = reader.prepare_cached(sql)?;
+        Ok(stmt.query_row(params, map_row).optional()?)
+    }
+
+    /// Runs a query on a reader connection, deserializing each row into a `T`.
+    ///
+    /// Rows are deserialized from a JSON object mapping column names to their values, so `T` is
+    /// typically a struct deriving [serde::Deserialize] with fields named after result columns.
+    /// Blobs are represented as arrays of bytes.
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_as<T: serde::de::DeserializeOwned, P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<Vec<T>, PowerSyncError> {
+        use rusqlite::types::ValueRef;
+        use serde_json::Value;
+
+        let reader = self.reader().await?;
+        let mut stmt = reader.prepare(sql)?;
+        let column_names: Vec<String> = stmt
+            .column_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let mut rows = stmt.query(params)?;
+
+        let mut results = vec![];
+        while let Some(row) = rows.next()? {
+            let mut object = serde_json::Map::with_capacity(column_names.len());
+            for (i, name) in column_names.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(value) => value.into(),
+                    // Non-finite values are represented as null.
+                    ValueRef::Real(value) => value.into(),
+                    ValueRef::Text(_) => Value::String(row.get(i)?),
+                    ValueRef::Blob(value) => value.into(),
+                };
+                object.insert(name.clone(), value);
+            }
+
+            results.push(serde_json::from_value(Value::Object(object))?);
+        }
+
+        Ok(results)
+    }
+
+    /// Runs a query on a reader connection, returning at most `limit` rows after skipping the
+    /// first `offset` rows.
+    ///
+    /// The `sql` must be a single `SELECT` statement using positional parameters, which is
+    /// wrapped in a subquery to apply the page bounds. It should have an `ORDER BY` clause for
+    /// pages to be stable. This fetches one additional row to report whether more rows are
+    /// available after the returned page, which makes [QueryPage::has_more] reliable without a
+    /// separate `COUNT` query.
+    ///
+    /// Unlike other query methods, this takes parameters as a slice instead of
+    /// [rusqlite::Params] because the limit and offset need to be bound after them, which
+    /// [rusqlite::Params] doesn't support.
+    #[cfg(feature = "rusqlite")]
+    pub async fn query_page<T>(
+        &self,
+        sql: &str,
+        params: &[&dyn rusqlite::ToSql],
+        limit: usize,
+        offset: usize,
+        map_row: impl FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<QueryPage<T>, PowerSyncError> {
+        let reader = self.reader().await?;
+        let sql = sql.trim_end().trim_end_matches(';');
+        let mut stmt = reader.prepare(&format!("SELECT * FROM ({sql}) LIMIT ? OFFSET ?"))?;
+
+        // Fetch an additional row to find out whether there are more rows after this page.
+        let fetch_limit = i64::try_from(limit)
+            .ok()
+            .and_then(|limit| limit.checked_add(1))
+            .ok_or_else(|| PowerSyncError::argument_error("limit is too large"))?;
+        let offset = i64::try_from(offset)
+            .map_err(|_| PowerSyncError::argument_error("offset is too large"))?;
+
+        let mut all_params = params.to_vec();
+        all_params.push(&fetch_limit);
+        all_params.push(&offset);
+
+        let mut items = stmt
+            .query_map(all_params.as_slice(), map_row)?
+            .collect::<Result<Vec<T>, _>>()?;
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+
+        Ok(QueryPage { items, has_more })
+    }
+
+    /// Reads the row with the given `id` from the view of `table`, mapping it with `map_row` or
+    /// returning [None] if no such row exists.
+    ///
+    /// The view name is resolved from the schema, taking [crate::schema::Table::view_name_override]
+    /// into account. Returns an error if the schema doesn't contain a table with the given name.
+    #[cfg(feature = "rusqlite")]
+    pub async fn get_row<T>(
+        &self,
+        table: &str,
+        id: &str,
+        map_row: impl FnOnce(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<Option<T>, PowerSyncError> {
+        let Some(view) = self.inner.schema.view_name(table) else {
+            return Err(PowerSyncError::argument_error(format!(
+                "{table} is not a table in the schema"
+            )));
+        };
+
+        self.query_opt(
+            &format!("SELECT * FROM \"{view}\" WHERE id = ?"),
+            rusqlite::params![id],
+            map_row,
+        )
+        .await
+    }
+
+    /// Runs a single write statement on the writer connection, returning the number of rows
+    /// affected by it.
+    #[cfg(feature = "rusqlite")]
+    pub async fn execute<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<usize, PowerSyncError> {
+        let writer = self.writer().await?;
+        Ok(writer.execute(sql, params)?)
+    }
+
+    /// Like [Self::execute], but rejects `UPDATE` and `DELETE` statements targeting the view of an
+    /// insert-only table with an error instead of running them.
+    ///
+    /// Only the start of the statement is inspected, so writes hidden in triggers or common table
+    /// expressions are not detected.
+    #[cfg(feature = "rusqlite")]
+    pub async fn execute_checked<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<usize, PowerSyncError> {
+        if let Some(target) = schema::update_or_delete_target(sql)
+            && self.inner.schema.is_insert_only_view(target)
+        {
+            return Err(PowerSyncError::argument_error(format!(
+                "{target} is an insert-only table, updates and deletes are not supported"
+            )));
+        }
+
+        self.execute(sql, params).await
+    }
+
+    /// Runs a single write statement and reports whether it added entries to the CRUD upload
+    /// queue.
+    ///
+    /// This is determined by comparing the latest `ps_crud` entry before and after running the
+    /// statement, which happens while holding the writer so that no other write can interfere.
+    #[cfg(feature = "rusqlite")]
+    pub async fn execute_tracked<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<crud::ExecResult, PowerSyncError> {
+        let writer = self.writer().await?;
+        let conn: &rusqlite::Connection = &writer;
+        let last_crud_id = || {
+            conn.query_row(
+                "SELECT ifnull(max(id), 0) FROM ps_crud",
+                rusqlite::params![],
+                |row| row.get::<_, i64>(0),
+            )
+        };
+
+        let before = last_crud_id()?;
+        let rows_affected = conn.execute(sql, params)?;
+        let after = last_crud_id()?;
+
+        Ok(crud::ExecResult {
+            rows_affected,
+            crud_generated: after != before,
+        })
+    }
+
+    /// Runs a write statement and attaches `metadata` to all CRUD entries it creates.
+    ///
+    /// This avoids having to write the hidden `_metadata` column in every statement. All tables
+    /// written to by the statement need to have [crate::schema::TableOptions::track_metadata]
+    /// enabled, otherwise the write is rolled back and an error is returned.
+    ///
+    /// The core extension has no way to set metadata outside of the `_metadata` column, so this
+    /// attaches `metadata` to the created CRUD entries after running the statement. Entries for
+    /// which the statement has written `_metadata` itself keep their metadata.
+    ///
+    /// Returns the number of rows affected by the statement.
+    #[cfg(feature = "rusqlite")]
+    pub async fn execute_with_metadata<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+        metadata: &str,
+    ) -> Result<usize, PowerSyncError> {
+        let mut writer = self.writer().await?;
+        let tx = writer.savepoint()?;
+
+        let last_crud_id: i64 = tx.query_row(
+            "SELECT ifnull(max(id), 0) FROM ps_crud",
+            rusqlite::params![],
+            |row| row.get(0),
+        )?;
+        let rows_affected = tx.execute(sql, params)?;
+
+        {
+            let mut stmt = tx.prepare(
+                "SELECT DISTINCT json_extract(data, '$.type') FROM ps_crud WHERE id > ?",
+            )?;
+            let mut rows = stmt.query(rusqlite::params![last_crud_id])?;
+            while let Some(row) = rows.next()? {
+                let table: String = row.get(0)?;
+                if !self.inner.schema.tracks_metadata(&table) {
+                    // Dropping the savepoint rolls the write back.
+                    return Err(PowerSyncError::argument_error(format!(
+                        "Table {table} does not track metadata"
+                    )));
+                }
+            }
+        }
+
+        tx.execute(
+            "UPDATE ps_crud SET data = json_set(data, '$.metadata', ?) \
+            WHERE id > ? AND ifnull(json_type(data, '$.metadata'), 'null') = 'null'",
+            rusqlite::params![metadata, last_crud_id],
+        )?;
+        tx.commit()?;
+        Ok(rows_affected)
+    }
+
+    /// Deletes all rows from a [crate::schema::TableOptions::local_only] table, returning the
+    /// number of rows removed.
+    ///
+    /// Returns an error if the schema doesn't contain a local-only table with the given name.
+    pub async fn clear_local_table(&self, name: &str) -> Result<u64, PowerSyncError> {
+        let Some(internal_name) = self.inner.schema.local_only_table(name) else {
+            return Err(PowerSyncError::argument_error(format!(
+                "{name} is not a local-only table in the schema"
+            )));
+        };
+
+        let writer = self.writer().await?;
+        let stmt = writer
+            .sqlite_connection()
+            .prepare(&format!("DELETE FROM \"{internal_name}\" RETURNING 1"))?;
+        let mut deleted = 0;
+        while stmt.step()? == ResultCode::ROW {
+            deleted += 1;
+        }
+
+        Ok(deleted)
+    }
+
     /// Returns a [Stream] traversing through transactions that have been completed on this
     /// database.
     ///
@@ -277,32 +837,281 @@ impl PowerSyncDatabase {
         stream.try_next().await
     }
 
+    /// Returns a [Stream] of all local writes that haven't been marked as completed, ordered by
+    /// their [CrudEntry::client_id].
+    ///
+    /// Unlike [Self::crud_transactions], this doesn't group entries by transaction, which can be
+    /// simpler for backends that apply writes one-by-one. After uploading entries, call
+    /// [Self::complete_crud_entries] with the last uploaded id.
+    pub fn crud_entries(&self) -> impl Stream<Item = Result<CrudEntry, PowerSyncError>> + '_ {
+        crud::crud_entries(self)
+    }
+
+    /// Marks all local writes up to and including `last_client_id` as completed, removing them
+    /// from the upload queue.
+    ///
+    /// The optional `write_checkpoint` has the same meaning as in
+    /// [CrudTransaction::complete_with_checkpoint].
+    pub async fn complete_crud_entries(
+        &self,
+        last_client_id: i64,
+        write_checkpoint: Option<i64>,
+    ) -> Result<(), PowerSyncError> {
+        self.inner
+            .complete_crud_items(last_client_id, write_checkpoint)
+            .await
+    }
+
+    /// Waits until all local writes have been uploaded.
+    ///
+    /// The returned future completes once the upload queue is empty and no upload is in progress.
+    /// Note that uploads only happen while connected, so this doesn't complete while there are
+    /// pending writes and the database is disconnected.
+    pub async fn wait_for_uploads_complete(&self) -> Result<(), PowerSyncError> {
+        let mut status = self.watch_status();
+        while let Some(status) = status.next().await {
+            if status.is_uploading() || status.upload_error().is_some() {
+                continue;
+            }
+
+            let reader = self.reader().await?;
+            let stmt = reader
+                .sqlite_connection()
+                .prepare("SELECT 1 FROM ps_crud LIMIT 1")?;
+            if stmt.step()? == ResultCode::DONE {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the id identifying this client towards the PowerSync service.
+    ///
+    /// The id is generated when the database is first created and stays stable afterwards. It's
+    /// used to request write checkpoints, and can also be used to associate a device with its
+    /// uploads on the backend.
+    pub async fn client_id(&self) -> Result<String, PowerSyncError> {
+        self.inner.client_id().await
+    }
+
+    /// Requests a write checkpoint from the PowerSync service and returns its op id.
+    ///
+    /// Once a sync iteration has synced the returned checkpoint, all writes uploaded before calling
+    /// this method have been reflected by the service. This uses the connector passed to the last
+    /// [Self::connect] call and fails if the database hasn't been connected yet.
+    pub async fn request_write_checkpoint(&self) -> Result<i64, PowerSyncError> {
+        let options = {
+            let options = self.inner.last_options.lock().unwrap();
+            options.clone().ok_or_else(|| {
+                PowerSyncError::argument_error(
+                    "Write checkpoints can only be requested after calling connect()",
+                )
+            })?
+        };
+
+        let client_id = self.client_id().await?;
+        let credentials = options.credentials(self.inner.env.clock).await?;
+        write_checkpoint(&self.inner, &client_id, credentials, &options).await
+    }
+
+    /// Checks whether the connector and PowerSync service configured in `options` can be used to
+    /// sync, without starting the sync client.
+    ///
+    /// This fetches credentials, opens a sync stream and closes it again after receiving the first
+    /// sync line. Unlike [Self::connect], which retries failed connections in the background,
+    /// errors are returned directly. For instance, credentials rejected by the service result
+    /// in a [crate::error::PowerSyncErrorKind::InvalidCredentials] error.
+    ///
+    /// The sync request is the one [Self::connect] would send with the same `options`. Since it's
+    /// computed by the sync client (which needs to be running, see [Self::async_tasks]), this
+    /// fails while the database is connected or connecting.
+    pub async fn test_connection(&self, options: &SyncOptions) -> Result<(), PowerSyncError> {
+        let request = self.sync.resolve_sync_request(options.clone()).await?;
+        let credentials = options.credentials(self.inner.env.clock).await?;
+        test_sync_stream(self.inner.clone(), credentials, request, options).await
+    }
+
     /// Returns the current [SyncStatusData] snapshot reporting the sync state of this database.
     pub fn status(&self) -> Arc<SyncStatusData> {
         self.inner.status.current_snapshot()
     }
 
+    /// Loads the sync status persisted in the database, such as the time of the last completed
+    /// sync, into [Self::status].
+    ///
+    /// This can be called right after opening the database to show the previous sync state
+    /// before connecting. It does nothing while the sync client is connected or connecting, since
+    /// the status is kept up-to-date by the sync client in that case.
+    pub async fn refresh_offline_status(&self) -> Result<(), PowerSyncError> {
+        let reader = self.inner.reader().await?;
+        let status = &self.inner.status;
+        if status.current_snapshot().connection_state() != ConnectionState::Disconnected {
+            return Ok(());
+        }
+
+        status.update(|s| s.resolve_offline_state(reader.sqlite_connection(), self.inner.env.clock))
+    }
+
     /// Returns an updating [Stream] of [SyncStatusData] events emitting every time the status is
     /// changed.
     pub fn watch_status<'a>(&'a self) -> impl Stream<Item = Arc<SyncStatusData>> + 'a {
         self.inner.watch_status()
     }
 
+    /// Returns a [Stream] emitting every revision of the sync status made after this call.
+    ///
+    /// Unlike [Self::watch_status], which only emits the latest status when polled, this buffers
+    /// up to `capacity` revisions for consumers that need to observe every transition (e.g. an
+    /// upload going from an error back to idle). If the consumer falls behind, the oldest buffered
+    /// revisions are dropped, so that the latest status is always emitted eventually. A `capacity`
+    /// of `0` is treated as `1`.
+    pub fn watch_status_all(
+        &self,
+        capacity: usize,
+    ) -> impl Stream<Item = Arc<SyncStatusData>> + 'static {
+        let (send, receive) = async_channel::bounded(capacity.max(1));
+        let listener = self.on_status_changed(move |status| {
+            // Drop the oldest revision if the buffer is full.
+            let _ = send.force_send(status);
+        });
+
+        futures_lite::stream::unfold((receive, listener), |(receive, listener)| async move {
+            let status = receive.recv().await.ok()?;
+            Some((status, (receive, listener)))
+        })
+    }
+
+    /// Registers a `callback` that is invoked with the new [SyncStatusData] every time the status
+    /// is changed.
+    ///
+    /// This is an alternative to [Self::watch_status] for callback-oriented code. Unlike the
+    /// stream, the callback is invoked for every revision of the status, in the order in which
+    /// revisions were installed. It runs synchronously on the task updating the status while
+    /// further updates wait, so it should return quickly. The callback stays registered until the
+    /// returned [StatusChangeListener] is dropped.
+    pub fn on_status_changed(
+        &self,
+        callback: impl Fn(Arc<SyncStatusData>) + Send + Sync + 'static,
+    ) -> StatusChangeListener {
+        self.inner.status.listener(callback)
+    }
+
+    /// Returns a [Stream] emitting the [ConnectionState] of the sync client.
+    ///
+    /// Unlike [Self::watch_status], this only emits an item when the connection state changes,
+    /// ignoring other updates like download progress.
+    pub fn watch_connection_state<'a>(&'a self) -> impl Stream<Item = ConnectionState> + 'a {
+        let mut last_state = None;
+        self.watch_status().filter_map(move |status| {
+            let state = status.connection_state();
+            if last_state == Some(state) {
+                None
+            } else {
+                last_state = Some(state);
+                Some(state)
+            }
+        })
+    }
+
+    /// Returns a [Stream] emitting a [StreamPriority] every time all streams with that priority
+    /// (or a higher one) have completed syncing.
+    ///
+    /// This only emits completions happening after the stream is first polled. Completing a full
+    /// sync across all priorities is reported as [StreamPriority::SENTINEL]. This can be used to
+    /// progressively reveal parts of the UI as more data becomes available.
+    pub fn watch_priority_completions<'a>(&'a self) -> impl Stream<Item = StreamPriority> + 'a {
+        let mut seen = None::<HashSet<_>>;
+        self.watch_status().flat_map(move |status| {
+            let synced: HashSet<_> = status
+                .synced_priorities()
+                .map(|(priority, last_synced_at)| (priority, last_synced_at.map(|t| t.0)))
+                .collect();
+
+            let mut completed: Vec<StreamPriority> = match &seen {
+                Some(seen) => synced
+                    .difference(seen)
+                    .map(|(priority, _)| *priority)
+                    .collect(),
+                // The first status only marks completions that happened before we started
+                // listening.
+                None => Vec::new(),
+            };
+            completed.sort_by_key(|priority| priority.priority_number());
+            completed.dedup();
+            seen = Some(synced);
+
+            futures_lite::stream::iter(completed)
+        })
+    }
+
     /// Creates a [SyncStream] based on name and optional parameters.
     ///
     /// PowerSync will sync data from the requested stream when calling [SyncStream::subscribe].
     /// After the subscription handle returned by that method is dropped, PowerSync will continue
     /// syncing the stream for a specified amount of time as a local cache.
+    ///
+    /// This returns an error if `parameters` is not a JSON object.
     pub fn sync_stream<'a>(
         &'a self,
         name: &'a str,
         parameters: Option<&serde_json::Value>,
-    ) -> SyncStream<'a> {
-        SyncStream::new(
-            self,
-            name,
-            parameters.map(|e| e.as_object().expect("Parameters should be a JSON object")),
-        )
+    ) -> Result<SyncStream<'a>, PowerSyncError> {
+        let stream = SyncStream::new(self, name, None);
+        match parameters {
+            Some(parameters) => stream.with_parameters(parameters),
+            None => Ok(stream),
+        }
+    }
+
+    /// Returns whether the schema passed to this database differs from the schema used when the
+    /// database was last opened.
+    ///
+    /// This also returns `true` when the database is opened for the first time. If the database
+    /// hasn't been initialized yet, this initializes it first.
+    pub async fn schema_changed_since_last_open(&self) -> Result<bool, PowerSyncError> {
+        self.inner.schema_changed().await
+    }
+
+    /// Subscribes to multiple [SyncStream]s at once.
+    ///
+    /// This is equivalent to calling [SyncStream::subscribe_with] for each stream, but only
+    /// notifies the sync client about changed subscriptions once. When connected, that avoids
+    /// reconnecting once for each stream.
+    pub async fn subscribe_many(
+        &self,
+        streams: &[(SyncStream<'_>, StreamSubscriptionOptions)],
+    ) -> Result<Vec<StreamSubscription>, PowerSyncError> {
+        streams::subscribe_many(self, streams).await
+    }
+
+    /// Attaches the SQLite database at `path` under the schema name `alias` on all connections
+    /// used by this database.
+    ///
+    /// This allows joining synced data against other databases. Reader connections attach the
+    /// database the next time they're used.
+    pub async fn attach(&self, alias: &str, path: impl AsRef<Path>) -> Result<(), PowerSyncError> {
+        let Some(path) = path.as_ref().to_str() else {
+            return Err(PowerSyncError::argument_error(
+                "Path of attached database must be valid UTF-8",
+            ));
+        };
+
+        self.inner.env.pool.attach(alias, path).await
+    }
+
+    /// Detaches a database previously attached with [Self::attach].
+    pub async fn detach(&self, alias: &str) -> Result<(), PowerSyncError> {
+        self.inner.env.pool.detach(alias).await
+    }
+
+    /// Returns the version of the PowerSync core extension used by this database.
+    ///
+    /// This includes the commit the extension was built from, which is useful to include in logs
+    /// or bug reports.
+    pub async fn core_version(&self) -> Result<CoreExtensionVersion, PowerSyncError> {
+        let reader = self.reader().await?;
+        CoreExtensionVersion::from_db(reader.sqlite_connection())
     }
 
     /// Obtains a [LeasedConnection] that can be used to run read-only queries on this database.
@@ -310,11 +1119,88 @@ impl PowerSyncDatabase {
         self.inner.reader().await
     }
 
+    /// Obtains up to `count` [LeasedConnection]s for read-only queries at once.
+    ///
+    /// This waits for at least one connection, see [crate::ConnectionPool::readers] for details.
+    pub async fn readers(&self, count: usize) -> Result<Vec<LeasedConnection>, PowerSyncError> {
+        self.inner.readers(count).await
+    }
+
+    /// Like [Self::reader], but ensures the connection observes all writes committed before this
+    /// method was called.
+    ///
+    /// Outside of transactions, SQLite reader connections start a new snapshot for each statement.
+    /// A transaction left open on a reader would pin an older snapshot though, so this method rolls
+    /// back such transactions before returning the connection.
+    pub async fn reader_fresh(&self) -> Result<LeasedConnection, PowerSyncError> {
+        let reader = self.reader().await?;
+        let connection = reader.sqlite_connection();
+        if !connection.is_autocommit() {
+            connection.exec(c"ROLLBACK")?;
+        }
+
+        Ok(reader)
+    }
+
     /// Obtains a [LeasedConnection] allowing reading and writing queries.
     pub async fn writer(&self) -> Result<LeasedConnection, PowerSyncError> {
         self.inner.writer().await
     }
 
+    /// Like [Self::reader], but fails if no connection could be obtained within `timeout`.
+    ///
+    /// This can be used to turn deadlocks caused by leaked leases into errors.
+    pub async fn reader_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<LeasedConnection, PowerSyncError> {
+        self.lease_with_timeout(self.reader(), timeout, "reader")
+            .await
+    }
+
+    /// Like [Self::writer], but fails if the connection could not be obtained within `timeout`.
+    ///
+    /// This can be used to turn deadlocks caused by leaked leases into errors.
+    pub async fn writer_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<LeasedConnection, PowerSyncError> {
+        self.lease_with_timeout(self.writer(), timeout, "writer")
+            .await
+    }
+
+    async fn lease_with_timeout(
+        &self,
+        lease: impl Future<Output = Result<LeasedConnection, PowerSyncError>>,
+        timeout: Duration,
+        kind: &'static str,
+    ) -> Result<LeasedConnection, PowerSyncError> {
+        let expired = async {
+            self.inner.env.timer.delay_once(timeout).await;
+            Err(RawPowerSyncError::LeaseTimeout { kind }.into())
+        };
+
+        future::or(lease, expired).await
+    }
+
+    /// Synchronously obtains a [LeasedConnection] for read-only queries, blocking the current
+    /// thread until a connection is available.
+    ///
+    /// If the database hasn't been initialized yet, this also initializes it first. This must not
+    /// be called from within an async context.
+    pub fn reader_blocking(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.inner.reader_blocking()
+    }
+
+    /// Synchronously obtains a [LeasedConnection] for reading and writing queries, blocking the
+    /// current thread until the connection is available.
+    ///
+    /// If the database hasn't been initialized yet, this also initializes it first. This must not
+    /// be called from within an async context.
+    pub fn writer_blocking(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.inner.writer_blocking()
+    }
+
     /*
     /// Returns the shared [InnerPowerSyncState] backing this database.
     ///
@@ -351,6 +1237,15 @@ impl PowerSyncDatabase {
      */
 }
 
+/// A page of rows returned by [PowerSyncDatabase::query_page].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPage<T> {
+    /// The rows in this page.
+    pub items: Vec<T>,
+    /// Whether there are more rows after this page.
+    pub has_more: bool,
+}
+
 impl Debug for PowerSyncDatabase {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PowerSyncDatabase").finish_non_exhaustive()
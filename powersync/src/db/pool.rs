@@ -1,17 +1,30 @@
 #[cfg(feature = "rusqlite")]
 use std::ops::{Deref, DerefMut};
-use std::{collections::HashSet, mem::MaybeUninit, path::Path, sync::Arc};
+use std::{
+    collections::HashSet,
+    mem::MaybeUninit,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use async_channel::{Receiver, Sender};
-use async_lock::{Mutex, MutexGuardArc};
-use powersync_sqlite_nostd::ResultCode;
+use async_lock::{Mutex, MutexGuardArc, Semaphore, SemaphoreGuardArc};
+use futures_lite::{Stream, StreamExt};
+use log::warn;
 use powersync_sqlite_nostd::bindings::{
     SQLITE_OPEN_CREATE, SQLITE_OPEN_READONLY, SQLITE_OPEN_READWRITE,
 };
+use powersync_sqlite_nostd::{Destructor, ResultCode};
 use serde::Deserialize;
 
-use crate::db::connection::{RawSqliteConnection, SqliteConnection};
-use crate::{db::watch::TableNotifiers, error::PowerSyncError};
+use crate::db::connection::{RawSqliteConnection, SqliteConnection, exec_stmt};
+use crate::{
+    db::watch::{ListenerConfiguration, TableNotifiers},
+    error::{PowerSyncError, RawPowerSyncError},
+};
 
 /// A raw connection pool, giving out both synchronous and asynchronous leases to SQLite
 /// connections as well as managing update hooks.
@@ -21,27 +34,140 @@ pub struct ConnectionPool {
 }
 
 impl ConnectionPool {
-    fn prepare_writer(connection: SqliteConnection) -> Arc<Mutex<SqliteConnection>> {
+    const CLOSED: &'static str = "connection pool has been closed";
+
+    fn prepare_writer(connection: SqliteConnection) -> Arc<Mutex<Option<SqliteConnection>>> {
         connection
             .exec(c"SELECT powersync_update_hooks('install');")
             .expect("could not install update hook");
+        Self::prepare_connection(&connection);
 
-        Arc::new(Mutex::new(connection))
+        Arc::new(Mutex::new(Some(connection)))
+    }
+
+    /// Installs functions provided by the SDK on a writer or reader connection.
+    fn prepare_connection(_connection: &SqliteConnection) {
+        #[cfg(feature = "uuid")]
+        crate::db::uuid::register_uuid7(_connection).expect("could not register uuid7 function");
     }
 
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PowerSyncError> {
+        Self::open_with_options(path, &PoolOptions::default())
+    }
+
+    /// Opens a pool like [Self::open], configuring connections with the given [PoolOptions].
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &PoolOptions,
+    ) -> Result<Self, PowerSyncError> {
+        Self::open_with_readers(path, options, None)
+    }
+
+    /// Opens a pool like [Self::open], loading the PowerSync core extension from the shared
+    /// library at `extension` on every connection.
+    ///
+    /// This is an alternative to [crate::env::PowerSyncEnvironment::powersync_auto_extension] for
+    /// apps distributing the core extension as a separate library.
+    #[cfg(feature = "dynamic-extension")]
+    pub fn open_with_extension<P: AsRef<Path>, E: AsRef<Path>>(
+        path: P,
+        extension: E,
+    ) -> Result<Self, PowerSyncError> {
+        Self::open_with_readers(path, &PoolOptions::default(), Some(extension.as_ref()))
+    }
+
+    /// Opens a pool with a single writer and a single reader connection.
+    ///
+    /// Compared to [Self::open], this uses less memory while still allowing reads to run
+    /// concurrently with writes. Compared to [Self::single_connection], reads don't have to wait
+    /// for the writer.
+    pub fn open_minimal<P: AsRef<Path>>(path: P) -> Result<Self, PowerSyncError> {
+        let mut options = PoolOptions::default();
+        options.with_reader_count(1);
+        Self::open_with_readers(path, &options, None)
+    }
+
+    fn open_with_readers<P: AsRef<Path>>(
+        path: P,
+        options: &PoolOptions,
+        extension: Option<&Path>,
+    ) -> Result<Self, PowerSyncError> {
         let writer = SqliteConnection::from(RawSqliteConnection::open_path(
             &path,
             SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
         )?);
+        Self::load_extension(&writer, extension)?;
 
         writer.exec(c"PRAGMA journal_mode = WAL")?;
         writer.exec(c"PRAGMA journal_size_limit = 6291456")?; // 6 * 1024 * 1024
         writer.exec(c"PRAGMA busy_timeout = 30000")?;
         writer.exec(c"PRAGMA cache_size = -51200")?; // -(50 * 1024)
 
+        let readers = Self::open_readers(&path, options.reader_count)?;
+        for reader in &readers {
+            Self::load_extension(reader, extension)?;
+        }
+
+        #[cfg(feature = "rusqlite")]
+        if let Some(capacity) = options.statement_cache_capacity {
+            for connection in std::iter::once(&writer).chain(&readers) {
+                connection
+                    .rusqlite_connection()
+                    .set_prepared_statement_cache_capacity(capacity);
+            }
+        }
+
+        Ok(Self::wrap_connections(writer, readers))
+    }
+
+    #[cfg(feature = "dynamic-extension")]
+    fn load_extension(
+        connection: &SqliteConnection,
+        extension: Option<&Path>,
+    ) -> Result<(), PowerSyncError> {
+        let Some(extension) = extension else {
+            return Ok(());
+        };
+
+        let connection = connection.rusqlite_connection();
+        unsafe {
+            // Safety: Loading the core extension is the documented purpose of this option.
+            let _guard = rusqlite::LoadExtensionGuard::new(connection)?;
+            connection.load_extension(extension, Some("sqlite3_powersync_init"))?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "dynamic-extension"))]
+    fn load_extension(
+        _connection: &SqliteConnection,
+        _extension: Option<&Path>,
+    ) -> Result<(), PowerSyncError> {
+        Ok(())
+    }
+
+    /// Creates a pool using an existing `writer` connection, opening `reader_count` additional
+    /// read-only connections to the database at `path`.
+    ///
+    /// This allows reusing a writer that has already been configured, e.g. by loading extensions
+    /// or attaching databases. The writer is used as-is, so it should be configured to use WAL
+    /// mode for readers to run concurrently with writes.
+    pub fn from_writer_and_path<P: AsRef<Path>>(
+        writer: impl Into<SqliteConnection>,
+        path: P,
+        reader_count: usize,
+    ) -> Result<Self, PowerSyncError> {
+        let readers = Self::open_readers(&path, reader_count)?;
+        Ok(Self::wrap_connections(writer, readers))
+    }
+
+    fn open_readers<P: AsRef<Path>>(
+        path: P,
+        count: usize,
+    ) -> Result<Vec<SqliteConnection>, PowerSyncError> {
         let mut readers = vec![];
-        for _ in 0..5 {
+        for _ in 0..count {
             let reader = SqliteConnection::from(RawSqliteConnection::open_path(
                 &path,
                 SQLITE_OPEN_READONLY,
@@ -50,7 +176,7 @@ impl ConnectionPool {
             readers.push(reader);
         }
 
-        Ok(Self::wrap_connections(writer, readers))
+        Ok(readers)
     }
 
     /// Creates a pool backed by a single write and multiple reader connections.
@@ -61,9 +187,16 @@ impl ConnectionPool {
         readers: impl IntoIterator<Item = impl Into<SqliteConnection>>,
     ) -> Self {
         let writer = Self::prepare_writer(writer.into());
-        let (release, consume) = async_channel::unbounded::<SqliteConnection>();
+        let (release, consume) = async_channel::unbounded::<PooledReader>();
         for reader in readers {
-            release.send_blocking(reader.into()).unwrap();
+            let reader = reader.into();
+            Self::prepare_connection(&reader);
+            release
+                .send_blocking(PooledReader {
+                    connection: reader,
+                    attachments_version: 0,
+                })
+                .unwrap();
         }
 
         Self {
@@ -72,8 +205,11 @@ impl ConnectionPool {
                 readers: Some(PoolReaders {
                     take_reader: consume,
                     release_reader: release,
+                    limit: None,
+                    in_use: AtomicUsize::new(0),
                 }),
                 table_notifiers: Default::default(),
+                attachments: Default::default(),
             }),
         }
     }
@@ -85,36 +221,185 @@ impl ConnectionPool {
                 writer: Self::prepare_writer(conn.into()),
                 readers: None,
                 table_notifiers: Default::default(),
+                attachments: Default::default(),
             }),
         }
     }
 
+    /// Limits the amount of reader connections that can be leased concurrently to `max`, which
+    /// can be used to bound memory usage from large result sets.
+    ///
+    /// This must be called before the pool is used. It has no effect on pools created with
+    /// [Self::single_connection], which don't have reader connections.
+    pub fn with_max_concurrent_readers(mut self, max: usize) -> Self {
+        let state = Arc::get_mut(&mut self.state).expect("pool should not be in use");
+        if let Some(readers) = &mut state.readers {
+            readers.limit = Some(Arc::new(Semaphore::new(max)));
+        }
+
+        self
+    }
+
+    /// Returns the amount of reader connections currently leased from this pool.
+    pub fn readers_in_use(&self) -> usize {
+        match &self.state.readers {
+            Some(readers) => readers.in_use.load(Ordering::SeqCst),
+            None => 0,
+        }
+    }
+
+    /// Returns a [Stream] emitting a [SqliteUpdateNotification] for tables updated by writes on
+    /// this pool.
+    ///
+    /// Notifications are emitted after writer leases have been returned to the pool. If the
+    /// consumer of the stream can't keep up, updated tables are merged into a single notification.
+    pub fn updates(&self) -> impl Stream<Item = SqliteUpdateNotification> + 'static {
+        self.state
+            .table_notifiers
+            .listen(ListenerConfiguration::all())
+            .map(|tables| SqliteUpdateNotification {
+                tables: Arc::new(tables),
+            })
+    }
+
     pub fn update_notifiers(&self) -> &Arc<TableNotifiers> {
         &self.state.table_notifiers
     }
 
-    fn take_connection_sync(&'_ self, writer: bool) -> LeasedConnection {
+    fn take_connection_sync(&'_ self, writer: bool) -> Result<LeasedConnection, PowerSyncError> {
         if !writer && let Some(readers) = &self.state.readers {
+            let permit = readers
+                .limit
+                .as_ref()
+                .map(|limit| limit.acquire_arc_blocking());
             let reader = readers
                 .take_reader
                 .recv_blocking()
-                .expect("should receive connection");
+                .map_err(|_| RawPowerSyncError::DatabaseClosed)?;
+            readers.in_use.fetch_add(1, Ordering::SeqCst);
 
-            LeasedConnection {
-                inner: OwnedConnectionLease::Reader {
-                    connection: MaybeUninit::new(reader),
-                    pool: self.clone(),
-                },
-            }
+            Ok(self.lease_reader(reader, permit))
         } else {
-            let guard = self.state.writer.lock_arc_blocking();
-            LeasedConnection {
-                inner: OwnedConnectionLease::Writer {
-                    connection: guard,
-                    pool: self.clone(),
-                },
+            self.lease_writer(self.state.writer.lock_arc_blocking())
+        }
+    }
+
+    fn lease_writer(
+        &self,
+        guard: MutexGuardArc<Option<SqliteConnection>>,
+    ) -> Result<LeasedConnection, PowerSyncError> {
+        if guard.is_none() {
+            return Err(RawPowerSyncError::DatabaseClosed.into());
+        }
+
+        Ok(LeasedConnection {
+            inner: OwnedConnectionLease::Writer {
+                connection: guard,
+                pool: self.clone(),
+            },
+        })
+    }
+
+    fn lease_reader(
+        &self,
+        mut reader: PooledReader,
+        permit: Option<SemaphoreGuardArc>,
+    ) -> LeasedConnection {
+        if let Err(e) = self.sync_attachments(&mut reader) {
+            warn!("Could not update attached databases on reader: {e}");
+        }
+
+        LeasedConnection {
+            inner: OwnedConnectionLease::Reader {
+                connection: MaybeUninit::new(reader.connection),
+                attachments_version: reader.attachments_version,
+                pool: self.clone(),
+                _permit: permit,
+            },
+        }
+    }
+
+    /// Attaches the database at `path` to all connections of this pool under the name `alias`.
+    ///
+    /// The database is attached to the writer immediately. Reader connections attach it the next
+    /// time they're leased.
+    pub async fn attach(&self, alias: &str, path: &str) -> Result<(), PowerSyncError> {
+        let writer = self.state.writer.lock().await;
+        let writer = writer.as_ref().ok_or(RawPowerSyncError::DatabaseClosed)?;
+        Self::attach_database(writer, alias, path)?;
+
+        let mut attachments = self.state.attachments.lock().unwrap();
+        attachments.version += 1;
+        attachments
+            .databases
+            .push((alias.to_string(), path.to_string()));
+        Ok(())
+    }
+
+    /// Detaches a database previously attached with [Self::attach] from all connections.
+    pub async fn detach(&self, alias: &str) -> Result<(), PowerSyncError> {
+        let writer = self.state.writer.lock().await;
+        let writer = writer.as_ref().ok_or(RawPowerSyncError::DatabaseClosed)?;
+        Self::detach_database(writer, alias)?;
+
+        let mut attachments = self.state.attachments.lock().unwrap();
+        attachments.version += 1;
+        attachments.databases.retain(|(name, _)| name != alias);
+        Ok(())
+    }
+
+    fn attach_database(
+        conn: &SqliteConnection,
+        alias: &str,
+        path: &str,
+    ) -> Result<(), PowerSyncError> {
+        let stmt = conn.prepare(&format!(
+            "ATTACH DATABASE ? AS \"{}\"",
+            alias.replace('"', "\"\"")
+        ))?;
+        stmt.bind_text(1, path, Destructor::TRANSIENT)?;
+        exec_stmt(stmt)
+    }
+
+    fn detach_database(conn: &SqliteConnection, alias: &str) -> Result<(), PowerSyncError> {
+        let stmt = conn.prepare(&format!(
+            "DETACH DATABASE \"{}\"",
+            alias.replace('"', "\"\"")
+        ))?;
+        exec_stmt(stmt)
+    }
+
+    /// Brings the databases attached to a reader connection in sync with [PoolState::attachments].
+    fn sync_attachments(&self, reader: &mut PooledReader) -> Result<(), PowerSyncError> {
+        let attachments = self.state.attachments.lock().unwrap();
+        if attachments.version == reader.attachments_version {
+            return Ok(());
+        }
+
+        let conn = &reader.connection;
+        let mut current = vec![];
+        {
+            let stmt = conn.prepare(
+                "SELECT name FROM pragma_database_list WHERE name NOT IN ('main', 'temp')",
+            )?;
+            while stmt.step()? == ResultCode::ROW {
+                current.push(stmt.column_text(0)?.to_string());
+            }
+        }
+
+        for alias in &current {
+            if !attachments.databases.iter().any(|(name, _)| name == alias) {
+                Self::detach_database(conn, alias)?;
+            }
+        }
+        for (alias, path) in &attachments.databases {
+            if !current.contains(alias) {
+                Self::attach_database(conn, alias, path)?;
             }
         }
+
+        reader.attachments_version = attachments.version;
+        Ok(())
     }
 
     fn take_update_notifications(
@@ -138,73 +423,229 @@ impl ConnectionPool {
         }
     }
 
-    async fn take_connection_async(&self, writer: bool) -> LeasedConnection {
+    async fn take_connection_async(
+        &self,
+        writer: bool,
+    ) -> Result<LeasedConnection, PowerSyncError> {
         if !writer && let Some(readers) = &self.state.readers {
+            let permit = match &readers.limit {
+                Some(limit) => Some(limit.acquire_arc().await),
+                None => None,
+            };
             let reader = readers
                 .take_reader
                 .recv()
                 .await
-                .expect("should receive connection");
+                .map_err(|_| RawPowerSyncError::DatabaseClosed)?;
+            readers.in_use.fetch_add(1, Ordering::SeqCst);
 
-            LeasedConnection {
-                inner: OwnedConnectionLease::Reader {
-                    connection: MaybeUninit::new(reader),
-                    pool: self.clone(),
-                },
-            }
+            Ok(self.lease_reader(reader, permit))
         } else {
-            let guard = self.state.writer.lock_arc().await;
-            LeasedConnection {
-                inner: OwnedConnectionLease::Writer {
-                    connection: guard,
-                    pool: self.clone(),
-                },
-            }
+            self.lease_writer(self.state.writer.lock_arc().await)
         }
     }
 
+    /// Leases the writer connection.
+    ///
+    /// # Panics
+    ///
+    /// If the pool has been closed with [Self::close], see [Self::try_writer] for a fallible
+    /// variant.
     pub async fn writer(&self) -> LeasedConnection {
-        self.take_connection_async(true).await
+        self.try_writer().await.expect(Self::CLOSED)
     }
 
     pub fn writer_sync(&self) -> LeasedConnection {
-        self.take_connection_sync(true)
+        self.try_writer_sync().expect(Self::CLOSED)
     }
 
+    /// Leases a reader connection.
+    ///
+    /// # Panics
+    ///
+    /// If the pool has been closed with [Self::close], see [Self::try_reader] for a fallible
+    /// variant.
     pub async fn reader(&self) -> LeasedConnection {
-        self.take_connection_async(false).await
+        self.try_reader().await.expect(Self::CLOSED)
     }
 
     pub fn reader_sync(&self) -> LeasedConnection {
+        self.try_reader_sync().expect(Self::CLOSED)
+    }
+
+    /// Like [Self::writer], but fails if the pool has been closed.
+    pub async fn try_writer(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.take_connection_async(true).await
+    }
+
+    /// Like [Self::writer_sync], but fails if the pool has been closed.
+    pub fn try_writer_sync(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.take_connection_sync(true)
+    }
+
+    /// Like [Self::reader], but fails if the pool has been closed.
+    pub async fn try_reader(&self) -> Result<LeasedConnection, PowerSyncError> {
+        self.take_connection_async(false).await
+    }
+
+    /// Like [Self::reader_sync], but fails if the pool has been closed.
+    pub fn try_reader_sync(&self) -> Result<LeasedConnection, PowerSyncError> {
         self.take_connection_sync(false)
     }
+
+    /// Closes all connections of this pool.
+    ///
+    /// This waits for the writer connection to be returned to the pool. Reader connections that
+    /// are currently leased are closed once they're returned. Afterwards, attempts to lease
+    /// connections fail.
+    pub async fn close(&self) {
+        if let Some(readers) = &self.state.readers {
+            readers.take_reader.close();
+            // Drop idle readers, which closes them.
+            while readers.take_reader.try_recv().is_ok() {}
+        }
+
+        self.state.writer.lock().await.take();
+    }
+
+    /// Leases up to `count` reader connections at once, e.g. to run independent queries in
+    /// parallel.
+    ///
+    /// This waits for one reader to become available and then additionally takes readers that are
+    /// available without waiting. Fewer than `count` connections are returned if the pool is
+    /// smaller or other readers are currently leased. Pools created with [Self::single_connection]
+    /// return at most one connection.
+    pub async fn readers(&self, count: usize) -> Vec<LeasedConnection> {
+        self.try_readers(count).await.expect(Self::CLOSED)
+    }
+
+    /// Like [Self::readers], but fails if the pool has been closed.
+    pub async fn try_readers(&self, count: usize) -> Result<Vec<LeasedConnection>, PowerSyncError> {
+        let mut leased = Vec::with_capacity(count);
+        if count == 0 {
+            return Ok(leased);
+        }
+
+        leased.push(self.try_reader().await?);
+        if let Some(readers) = &self.state.readers {
+            while leased.len() < count {
+                let permit = match &readers.limit {
+                    Some(limit) => match limit.try_acquire_arc() {
+                        Some(permit) => Some(permit),
+                        None => break,
+                    },
+                    None => None,
+                };
+                let Ok(reader) = readers.take_reader.try_recv() else {
+                    break;
+                };
+                readers.in_use.fetch_add(1, Ordering::SeqCst);
+
+                leased.push(self.lease_reader(reader, permit));
+            }
+        }
+
+        Ok(leased)
+    }
 }
 
+/// A notification about tables that have been written to, see [ConnectionPool::updates].
 #[derive(Clone, Deserialize)]
 #[serde(transparent)]
 pub struct SqliteUpdateNotification {
     tables: Arc<HashSet<String>>,
 }
 
+impl SqliteUpdateNotification {
+    /// The names of tables that have been updated.
+    pub fn tables(&self) -> &HashSet<String> {
+        &self.tables
+    }
+}
+
+/// Options for opening a [ConnectionPool] with [ConnectionPool::open_with_options].
+#[derive(Clone, Debug)]
+pub struct PoolOptions {
+    reader_count: usize,
+    #[cfg(feature = "rusqlite")]
+    statement_cache_capacity: Option<usize>,
+}
+
+impl PoolOptions {
+    /// Sets the amount of read-only connections opened in addition to the writer (the default is
+    /// 5).
+    pub fn with_reader_count(&mut self, count: usize) {
+        self.reader_count = count;
+    }
+
+    /// Sets the capacity of the prepared statement cache on all connections of the pool.
+    ///
+    /// The cache is used by [rusqlite::Connection::prepare_cached] as well as by
+    /// [crate::PowerSyncDatabase::query_one_cached] and queries run by
+    /// [crate::PowerSyncDatabase::watch_statement]. When not set, rusqlite's default capacity is
+    /// used.
+    #[cfg(feature = "rusqlite")]
+    pub fn with_statement_cache_capacity(&mut self, capacity: usize) {
+        self.statement_cache_capacity = Some(capacity);
+    }
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            reader_count: 5,
+            #[cfg(feature = "rusqlite")]
+            statement_cache_capacity: None,
+        }
+    }
+}
+
 struct PoolState {
-    writer: Arc<Mutex<SqliteConnection>>,
+    /// The writer connection, or [None] after the pool has been closed.
+    writer: Arc<Mutex<Option<SqliteConnection>>>,
     readers: Option<PoolReaders>,
     table_notifiers: Arc<TableNotifiers>,
+    /// Databases attached with [ConnectionPool::attach].
+    attachments: std::sync::Mutex<Attachments>,
+}
+
+#[derive(Default)]
+struct Attachments {
+    /// Incremented whenever [Self::databases] changes.
+    version: u64,
+    /// Pairs of aliases and paths of attached databases.
+    databases: Vec<(String, String)>,
+}
+
+/// A reader connection that is currently not leased.
+struct PooledReader {
+    connection: SqliteConnection,
+    /// The [Attachments::version] applied to this connection.
+    attachments_version: u64,
 }
 
 struct PoolReaders {
-    take_reader: Receiver<SqliteConnection>,
-    release_reader: Sender<SqliteConnection>,
+    take_reader: Receiver<PooledReader>,
+    release_reader: Sender<PooledReader>,
+    /// An optional limit on the amount of concurrently leased readers, configured with
+    /// [ConnectionPool::with_max_concurrent_readers].
+    limit: Option<Arc<Semaphore>>,
+    /// The amount of currently leased reader connections.
+    in_use: AtomicUsize,
 }
 
 enum OwnedConnectionLease {
     Writer {
-        connection: MutexGuardArc<SqliteConnection>,
+        /// The writer slot of the pool, which is always [Some] while leased.
+        connection: MutexGuardArc<Option<SqliteConnection>>,
         pool: ConnectionPool,
     },
     Reader {
         connection: MaybeUninit<SqliteConnection>,
+        attachments_version: u64,
         pool: ConnectionPool,
+        /// A permit for the reader limit of the pool, released after the connection.
+        _permit: Option<SemaphoreGuardArc>,
     },
 }
 
@@ -213,22 +654,29 @@ impl Drop for OwnedConnectionLease {
         match self {
             OwnedConnectionLease::Writer { connection, pool } => {
                 // Send update notifications for writes made on this connection while leased.
-                let _ = pool.take_update_notifications(connection);
+                if let Some(connection) = connection.as_ref() {
+                    let _ = pool.take_update_notifications(connection);
+                }
             }
-            OwnedConnectionLease::Reader { connection, pool } => {
+            OwnedConnectionLease::Reader {
+                connection,
+                attachments_version,
+                pool,
+                ..
+            } => {
                 let connection = std::mem::replace(connection, MaybeUninit::uninit());
                 let connection = unsafe {
                     // safety: Only dropped here
                     connection.assume_init()
                 };
 
-                pool.state
-                    .readers
-                    .as_ref()
-                    .unwrap()
-                    .release_reader
-                    .send_blocking(connection)
-                    .expect("should send connection into pool");
+                let readers = pool.state.readers.as_ref().unwrap();
+                readers.in_use.fetch_sub(1, Ordering::SeqCst);
+                // If the pool has been closed, this drops (and thus closes) the connection.
+                let _ = readers.release_reader.send_blocking(PooledReader {
+                    connection,
+                    attachments_version: *attachments_version,
+                });
             }
         }
     }
@@ -244,7 +692,9 @@ pub struct LeasedConnection {
 impl LeasedConnection {
     pub(crate) fn sqlite_connection(&self) -> &SqliteConnection {
         match &self.inner {
-            OwnedConnectionLease::Writer { connection, .. } => connection,
+            OwnedConnectionLease::Writer { connection, .. } => {
+                connection.as_ref().expect("should hold writer")
+            }
             OwnedConnectionLease::Reader { connection, .. } => unsafe {
                 // safety: This is initialized by default, and only uninitialized on Drop.
                 connection.assume_init_ref()
@@ -254,7 +704,9 @@ impl LeasedConnection {
 
     pub(crate) fn sqlite_connection_mut(&mut self) -> &mut SqliteConnection {
         match &mut self.inner {
-            OwnedConnectionLease::Writer { connection, .. } => connection,
+            OwnedConnectionLease::Writer { connection, .. } => {
+                connection.as_mut().expect("should hold writer")
+            }
             OwnedConnectionLease::Reader { connection, .. } => unsafe {
                 // safety: This is initialized by default, and only uninitialized on Drop.
                 connection.assume_init_mut()
@@ -18,6 +18,15 @@ impl Schema {
     /// Validates the schema by ensuring there are no duplicate table names and that each table is
     /// valid.
     pub fn validate(&self) -> Result<(), PowerSyncError> {
+        self.validate_with_column_limit(Table::DEFAULT_COLUMN_LIMIT)
+    }
+
+    /// Validates the schema like [Self::validate], checking column counts against the
+    /// `column_limit` of the SQLite connection the schema is applied to.
+    pub(crate) fn validate_with_column_limit(
+        &self,
+        column_limit: usize,
+    ) -> Result<(), PowerSyncError> {
         let mut table_names = HashSet::new();
         for table in &self.tables {
             if !table_names.insert(table.name.as_ref()) {
@@ -27,7 +36,7 @@ impl Schema {
                 )));
             }
 
-            table.validate()?;
+            table.validate_with_column_limit(column_limit)?;
         }
 
         for table in &self.raw_tables {
@@ -99,11 +108,23 @@ impl Table {
         table
     }
 
+    /// The name of the view through which rows of this table are read and written.
+    pub fn view_name(&self) -> &str {
+        self.view_name_override.as_deref().unwrap_or(&self.name)
+    }
+
     fn validate(&self) -> Result<(), PowerSyncError> {
-        if self.columns.len() > Self::MAX_AMOUNT_OF_COLUMNS {
+        self.validate_with_column_limit(Self::DEFAULT_COLUMN_LIMIT)
+    }
+
+    fn validate_with_column_limit(&self, column_limit: usize) -> Result<(), PowerSyncError> {
+        // The view for this table has an additional id column.
+        let max_columns = column_limit.saturating_sub(1);
+        if self.columns.len() > max_columns {
             return Err(PowerSyncError::argument_error(format!(
-                "Has more than {} columns, which is not supported",
-                Self::MAX_AMOUNT_OF_COLUMNS
+                "Table {} has {} columns, but at most {max_columns} are supported by SQLite",
+                self.name,
+                self.columns.len(),
             )));
         }
 
@@ -156,7 +177,9 @@ impl Table {
         Ok(())
     }
 
-    const MAX_AMOUNT_OF_COLUMNS: usize = 1999;
+    /// The default value of `SQLITE_MAX_COLUMN`, used when validating schemas without a
+    /// connection.
+    const DEFAULT_COLUMN_LIMIT: usize = 2000;
 }
 
 /// Options that apply to both view-based JSON tables and raw tables.
@@ -512,6 +535,106 @@ pub enum SchemaOrCustom {
     Custom(Box<SerializedJsonObject>),
 }
 
+impl SchemaOrCustom {
+    /// Whether writes on the table with the given name can carry metadata.
+    ///
+    /// For pre-serialized schemas, we can't inspect table options and assume this to be the case.
+    pub(crate) fn tracks_metadata(&self, table: &str) -> bool {
+        match self {
+            SchemaOrCustom::Schema(schema) => {
+                schema
+                    .tables
+                    .iter()
+                    .any(|t| t.name == table && t.options.track_metadata)
+                    || schema.raw_tables.iter().any(|t| {
+                        t.name == table
+                            && t.schema
+                                .as_ref()
+                                .is_some_and(|schema| schema.options.track_metadata)
+                    })
+            }
+            SchemaOrCustom::Custom(_) => true,
+        }
+    }
+
+    /// Returns the internal name of the table storing rows for a local-only table with the given
+    /// name, or [None] if no such table is part of the schema.
+    ///
+    /// For pre-serialized schemas, we can't inspect table options and always return [None].
+    pub(crate) fn local_only_table(&self, table: &str) -> Option<String> {
+        match self {
+            SchemaOrCustom::Schema(schema) => schema
+                .tables
+                .iter()
+                .find(|t| t.name == table && t.options.local_only)
+                .map(|t| format!("ps_data_local__{}", t.name)),
+            SchemaOrCustom::Custom(_) => None,
+        }
+    }
+
+    /// Returns the name of the view for the table with the given name, or [None] if no such table
+    /// is part of the schema.
+    ///
+    /// For pre-serialized schemas, we can't inspect view names and assume the view to be named
+    /// after the table.
+    pub(crate) fn view_name<'a>(&'a self, table: &'a str) -> Option<&'a str> {
+        match self {
+            SchemaOrCustom::Schema(schema) => schema
+                .tables
+                .iter()
+                .find(|t| t.name == table)
+                .map(Table::view_name),
+            SchemaOrCustom::Custom(_) => Some(table),
+        }
+    }
+
+    /// Whether `view` is the view of an insert-only table.
+    ///
+    /// For pre-serialized schemas, we can't inspect table options and assume this not to be the
+    /// case.
+    pub(crate) fn is_insert_only_view(&self, view: &str) -> bool {
+        match self {
+            SchemaOrCustom::Schema(schema) => schema
+                .tables
+                .iter()
+                .any(|t| t.view_name().eq_ignore_ascii_case(view) && t.options.insert_only),
+            SchemaOrCustom::Custom(_) => false,
+        }
+    }
+}
+
+/// Returns the name of the table or view written to by an `UPDATE` or `DELETE` statement, or
+/// [None] for other statements.
+///
+/// This only inspects the start of the statement (`UPDATE [OR <action>] <name>` and
+/// `DELETE FROM <name>`), which is enough to detect direct writes to a view.
+pub(crate) fn update_or_delete_target(sql: &str) -> Option<&str> {
+    let mut tokens = sql.split_whitespace();
+    let first = tokens.next()?;
+
+    let name = if first.eq_ignore_ascii_case("UPDATE") {
+        let mut name = tokens.next()?;
+        if name.eq_ignore_ascii_case("OR") {
+            // Skip the conflict resolution action.
+            tokens.next()?;
+            name = tokens.next()?;
+        }
+        name
+    } else if first.eq_ignore_ascii_case("DELETE") {
+        if !tokens.next()?.eq_ignore_ascii_case("FROM") {
+            return None;
+        }
+        tokens.next()?
+    } else {
+        return None;
+    };
+
+    // Strip the schema name (e.g. `main.`) and quotes around the identifier.
+    let name = name.trim_end_matches(';');
+    let name = name.rsplit('.').next()?;
+    Some(name.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']')))
+}
+
 impl From<Schema> for SchemaOrCustom {
     fn from(value: Schema) -> Self {
         Self::Schema(value)
@@ -529,6 +652,7 @@ impl From<&serde_json::value::RawValue> for SchemaOrCustom {
 
 #[cfg(test)]
 mod test {
+    use super::update_or_delete_target;
     use crate::schema::{Column, RawTable, RawTableSchema, Schema, Table, TrackPreviousValues};
     use serde_json::json;
 
@@ -605,6 +729,20 @@ mod test {
         );
     }
 
+    #[test]
+    fn finds_update_or_delete_target() {
+        for (sql, expected) in [
+            ("UPDATE users SET name = ?", Some("users")),
+            ("update or ignore \"users\" set name = ?", Some("users")),
+            ("DELETE FROM main.users WHERE id = ?", Some("users")),
+            ("DELETE FROM [users];", Some("users")),
+            ("INSERT INTO users (id) VALUES (uuid())", None),
+            ("SELECT * FROM users", None),
+        ] {
+            assert_eq!(update_or_delete_target(sql), expected, "{sql}");
+        }
+    }
+
     #[test]
     fn invalid_table_name() {
         let mut table = Table::create("#invalid-table", vec![], |_| {});
@@ -623,6 +761,19 @@ mod test {
         assert!(table.validate().is_err());
     }
 
+    #[test]
+    fn too_many_columns() {
+        let columns = (0..10).map(|i| Column::text(format!("c{i}"))).collect();
+        let table = Table::create("tbl", columns, |_| {});
+        assert!(table.validate_with_column_limit(11).is_ok());
+
+        let err = table
+            .validate_with_column_limit(10)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Table tbl has 10 columns"), "{err}");
+    }
+
     #[test]
     fn invalid_raw_table_missing_statements() {
         let mut table = RawTable::with_schema("users", RawTableSchema::default());
@@ -2,7 +2,7 @@ use crate::db::connection::{TransactionGuard, exec_stmt};
 use crate::{
     PowerSyncDatabase, StreamPriority,
     db::internal::InnerPowerSyncState,
-    error::PowerSyncError,
+    error::{PowerSyncError, RawPowerSyncError},
     sync::streams::{
         ChangedSyncSubscriptions, StreamDescription, StreamKey, SubscribeToStream,
         SubscriptionChangeRequest,
@@ -37,27 +37,104 @@ impl SyncStreamTracker {
         Arc<StreamSubscriptionGroup>,
         Option<ChangedSyncSubscriptions>,
     ) {
-        let mut streams = self.streams.lock().unwrap();
+        let (mut groups, changed) = self.reference_streams(db, std::slice::from_ref(key));
+        (groups.pop().unwrap(), changed)
+    }
 
-        if let Some(existing) = streams.get(key)
-            && let Some(active) = existing.upgrade()
-        {
-            return (active, None);
+    /// Obtains subscription groups for all `keys`, returning the new set of active streams if at
+    /// least one of them wasn't referenced before.
+    fn reference_streams(
+        &self,
+        db: &Arc<InnerPowerSyncState>,
+        keys: &[StreamKey],
+    ) -> (
+        Vec<Arc<StreamSubscriptionGroup>>,
+        Option<ChangedSyncSubscriptions>,
+    ) {
+        let mut streams = self.streams.lock().unwrap();
+        let mut groups = Vec::with_capacity(keys.len());
+        let mut did_change = false;
+
+        for key in keys {
+            if let Some(existing) = streams.get(key)
+                && let Some(active) = existing.upgrade()
+            {
+                groups.push(active);
+                continue;
+            }
+
+            let entry = Arc::new(StreamSubscriptionGroup {
+                db: db.clone(),
+                self_: Cell::default(),
+                key: key.clone(),
+            });
+
+            let weak_entry = Arc::downgrade(&entry);
+            entry.self_.set(Some(weak_entry.clone()));
+            streams.insert(key.clone(), weak_entry);
+            groups.push(entry);
+            did_change = true;
         }
 
-        let entry = Arc::new(StreamSubscriptionGroup {
-            db: db.clone(),
-            self_: Cell::default(),
-            key: key.clone(),
-        });
+        let changed =
+            did_change.then(|| ChangedSyncSubscriptions(streams.keys().cloned().collect()));
+        (groups, changed)
+    }
+}
+
+/// Subscribes to multiple sync streams at once, see [PowerSyncDatabase::subscribe_many].
+pub(crate) async fn subscribe_many(
+    db: &PowerSyncDatabase,
+    streams: &[(SyncStream<'_>, StreamSubscriptionOptions)],
+) -> Result<Vec<StreamSubscription>, PowerSyncError> {
+    let commands: Vec<_> = streams
+        .iter()
+        .map(|(stream, options)| {
+            SubscriptionChangeRequest::Subscribe(SubscribeToStream {
+                stream: stream.into(),
+                ttl: options.ttl,
+                priority: options.priority,
+            })
+        })
+        .collect();
+    subscription_commands(db, &commands).await?;
+    db.sync.resolve_offline_sync_status().await;
+
+    let keys: Vec<StreamKey> = streams
+        .iter()
+        .map(|(stream, _)| StreamDescription::from(stream).into())
+        .collect();
+    let (groups, changed) = db.inner.current_streams.reference_streams(&db.inner, &keys);
+
+    if let Some(changed) = changed {
+        db.sync.handle_subscriptions_changed(changed).await?;
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|group| StreamSubscription { group })
+        .collect())
+}
 
-        let weak_entry = Arc::downgrade(&entry);
-        entry.self_.set(Some(weak_entry.clone()));
-        streams.insert(key.clone(), weak_entry);
+/// Forwards subscription changes to the core extension in a single transaction.
+async fn subscription_commands(
+    db: &PowerSyncDatabase,
+    commands: &[SubscriptionChangeRequest<'_>],
+) -> Result<(), PowerSyncError> {
+    let mut writer = db.writer().await?;
+    let writer = TransactionGuard::new(writer.sqlite_connection_mut())?;
 
-        let all = streams.keys().cloned().collect();
-        (entry, Some(ChangedSyncSubscriptions(all)))
+    for cmd in commands {
+        let serialized = serde_json::to_string(cmd)?;
+        let stmt = writer.inner.prepare("SELECT powersync_control(?, ?)")?;
+        stmt.bind_text(1, "subscriptions", Destructor::STATIC)?;
+        // Fine because we drop the statement before serialized
+        stmt.bind_text(2, &serialized, Destructor::STATIC)?;
+        exec_stmt(stmt)?;
     }
+
+    writer.commit()?;
+    Ok(())
 }
 
 pub struct SyncStream<'a> {
@@ -79,25 +156,26 @@ impl<'a> SyncStream<'a> {
         }
     }
 
+    /// Returns a copy of this stream descriptor using the given `parameters`, which must be a
+    /// JSON object.
+    pub fn with_parameters(self, parameters: &serde_json::Value) -> Result<Self, PowerSyncError> {
+        let Some(parameters) = parameters.as_object() else {
+            return Err(PowerSyncError::argument_error(
+                "Stream parameters must be a JSON object",
+            ));
+        };
+
+        Ok(Self {
+            parameters: Some(SerializedJsonObject::from_value(parameters)),
+            ..self
+        })
+    }
+
     async fn subscription_command<'b>(
         &self,
         cmd: &SubscriptionChangeRequest<'b>,
     ) -> Result<(), PowerSyncError> {
-        let serialized = serde_json::to_string(cmd)?;
-
-        let mut writer = self.db.writer().await?;
-        let writer = TransactionGuard::new(writer.sqlite_connection_mut())?;
-
-        {
-            let stmt = writer.inner.prepare("SELECT powersync_control(?, ?)")?;
-            stmt.bind_text(1, "subscriptions", Destructor::STATIC)?;
-            // Fine because we drop the statement before serialized
-            stmt.bind_text(2, &serialized, Destructor::STATIC)?;
-            exec_stmt(stmt)?;
-        }
-
-        writer.commit()?;
-        Ok(())
+        subscription_commands(self.db, std::slice::from_ref(cmd)).await
     }
 
     /// Subscribes to this sync stream with the default options.
@@ -127,7 +205,7 @@ impl<'a> SyncStream<'a> {
             .reference_stream(&self.db.inner, &desc.into());
 
         if let Some(changed) = changed {
-            self.db.sync.handle_subscriptions_changed(changed).await;
+            self.db.sync.handle_subscriptions_changed(changed).await?;
         }
 
         Ok(StreamSubscription { group: stream })
@@ -162,7 +240,6 @@ impl<'a> From<&'a SyncStream<'a>> for StreamDescription<'a> {
 
 /// Options customizing a stream subscription, passed to [SyncStream::subscribe_with].
 #[derive(Default, Clone, Copy)]
-
 pub struct StreamSubscriptionOptions {
     ttl: Option<Duration>,
     priority: Option<StreamPriority>,
@@ -174,16 +251,38 @@ impl StreamSubscriptionOptions {
     /// When all [StreamSubscription]s for a sync stream are dropped, PowerSync normally stops
     /// requesting the stream after a while. The duration these streams are kept active can be
     /// configured here.
-    pub fn with_ttl(&mut self, ttl: Duration) -> &mut Self {
-        self.ttl = Some(ttl);
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.set_ttl(ttl);
         self
     }
 
     /// Request a subscription to the sync stream with a given [StreamPriority].
-    pub fn with_priority(&mut self, priority: StreamPriority) -> &mut Self {
+    pub fn with_priority(mut self, priority: StreamPriority) -> Self {
+        self.set_priority(priority);
+        self
+    }
+
+    /// Like [Self::with_ttl], but modifies these options in place.
+    pub fn set_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Like [Self::with_priority], but modifies these options in place.
+    pub fn set_priority(&mut self, priority: StreamPriority) -> &mut Self {
         self.priority = Some(priority);
         self
     }
+
+    /// The time-to-live configured with [Self::with_ttl], if any.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// The priority configured with [Self::with_priority], if any.
+    pub fn priority(&self) -> Option<StreamPriority> {
+        self.priority
+    }
 }
 
 struct StreamSubscriptionGroup {
@@ -232,6 +331,33 @@ impl StreamSubscription {
     pub fn unsubscribe(self) {
         drop(self);
     }
+
+    /// Drops this subscription and, if it was the last one for its stream, waits for the sync
+    /// client to be informed about the changed subscriptions.
+    ///
+    /// Unlike [Self::unsubscribe], this makes it possible to wait for the stream to no longer be
+    /// requested in subsequent sync iterations (after its TTL has expired).
+    ///
+    /// This returns an error if the database has been closed or dropped before the sync client
+    /// has acknowledged the change.
+    pub async fn unsubscribe_now(self) -> Result<(), PowerSyncError> {
+        let db = self.group.db.clone();
+        let key = self.group.key.clone();
+        drop(self);
+
+        let sync = db.sync.upgrade().ok_or(RawPowerSyncError::DatabaseClosed)?;
+        let is_referenced = {
+            let streams = db.current_streams.streams.lock().unwrap();
+            streams.contains_key(&key)
+        };
+        if !is_referenced {
+            let active = db.current_streams.collect_active_streams();
+            sync.handle_subscriptions_changed(ChangedSyncSubscriptions(active))
+                .await?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<'a> From<&'a StreamSubscription> for StreamDescription<'a> {
@@ -242,3 +368,28 @@ impl<'a> From<&'a StreamSubscription> for StreamDescription<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::StreamSubscriptionOptions;
+    use crate::StreamPriority;
+
+    #[test]
+    fn options_getters() {
+        let options = StreamSubscriptionOptions::default();
+        assert_eq!(options.ttl(), None);
+        assert_eq!(options.priority(), None);
+
+        let options = StreamSubscriptionOptions::default()
+            .with_ttl(Duration::from_secs(60))
+            .with_priority(StreamPriority::ONE);
+        assert_eq!(options.ttl(), Some(Duration::from_secs(60)));
+        assert_eq!(options.priority(), Some(StreamPriority::ONE));
+
+        let mut options = options;
+        options.set_ttl(Duration::ZERO);
+        assert_eq!(options.ttl(), Some(Duration::ZERO));
+    }
+}
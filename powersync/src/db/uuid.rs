@@ -0,0 +1,43 @@
+use std::ffi::c_int;
+
+use powersync_sqlite_nostd::{self as sqlite, Connection, Context};
+
+use crate::{
+    db::connection::SqliteConnection,
+    error::{PowerSyncError, RawPowerSyncError},
+};
+
+/// Registers the `uuid7()` SQL function on the connection, generating time-ordered UUIDv7 strings.
+///
+/// Unlike the random `uuid()` function provided by the core extension, these ids are ordered by
+/// creation time, which improves index locality when used as primary keys.
+pub(crate) fn register_uuid7(conn: &SqliteConnection) -> Result<(), PowerSyncError> {
+    unsafe extern "C" fn uuid7(
+        ctx: *mut sqlite::context,
+        _argc: c_int,
+        _argv: *mut *mut sqlite::value,
+    ) {
+        let id = uuid::Uuid::now_v7().hyphenated().to_string();
+        ctx.result_text_transient(&id);
+    }
+
+    let db = unsafe {
+        // Safety: Registering functions doesn't change the state of the connection.
+        conn.handle()
+    };
+    db.create_function_v2(
+        "uuid7",
+        0,
+        sqlite::UTF8,
+        None,
+        Some(uuid7),
+        None,
+        None,
+        None,
+    )
+    .map_err(|code| RawPowerSyncError::RawSqlite {
+        code,
+        context: "Could not register uuid7() function".into(),
+    })?;
+    Ok(())
+}
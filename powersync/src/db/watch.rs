@@ -17,9 +17,11 @@ pub struct TableNotifiers {
 
 impl TableNotifiers {
     pub fn notify_updates(&self, updates: &HashSet<String>) {
-        let guard = self.active.lock().unwrap();
+        // Dispatch without holding the lock, so that callbacks can drop listeners (which
+        // unregisters them) or register new ones.
+        let listeners = self.active.lock().unwrap().clone();
 
-        for listener in &*guard {
+        for listener in &listeners {
             listener.dispatch_updates(updates);
         }
     }
@@ -45,6 +47,36 @@ impl TableNotifiers {
             current_waiter: None,
         }
     }
+
+    /// Registers a `callback` invoked synchronously whenever one of the `tables` is updated.
+    ///
+    /// The callback is unregistered when the returned [TableChangeListener] is dropped.
+    pub fn listen_callback(
+        self: &Arc<Self>,
+        tables: HashSet<String>,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> TableChangeListener {
+        let listener = Arc::new(TableListenerState {
+            notifiers: self.clone(),
+            notifier: Event::new(),
+            config: ListenerConfigurationInner::Callback(EmitToCallback {
+                filter: tables,
+                callback: Box::new(callback),
+            }),
+        });
+
+        {
+            let mut guard = self.active.lock().unwrap();
+            guard.push(listener.clone());
+        }
+
+        TableChangeListener { state: listener }
+    }
+
+    fn remove(&self, state: &Arc<TableListenerState>) {
+        let mut guard = self.active.lock().unwrap();
+        guard.retain(|listener| !Arc::ptr_eq(listener, state));
+    }
 }
 
 struct TableListener {
@@ -80,8 +112,20 @@ impl Stream for TableListener {
 
 impl Drop for TableListener {
     fn drop(&mut self) {
-        let mut guard = self.state.notifiers.active.lock().unwrap();
-        guard.retain(|listener| !Arc::ptr_eq(listener, &self.state));
+        self.state.notifiers.remove(&self.state);
+    }
+}
+
+/// A callback registered with [crate::PowerSyncDatabase::on_tables_changed].
+///
+/// Dropping this handle unregisters the callback.
+pub struct TableChangeListener {
+    state: Arc<TableListenerState>,
+}
+
+impl Drop for TableChangeListener {
+    fn drop(&mut self) {
+        self.state.notifiers.remove(&self.state);
     }
 }
 
@@ -96,6 +140,7 @@ pub struct ListenerConfiguration(ListenerConfigurationInner);
 enum ListenerConfigurationInner {
     IfMatches(EmitIfMatches),
     All(EmitAll),
+    Callback(EmitToCallback),
 }
 
 impl ListenerConfiguration {
@@ -132,10 +177,18 @@ impl ListenerConfigurationInner {
 
                 Some(take(state.deref_mut()))
             }
+            // Callbacks are invoked when dispatching updates, there's nothing to consume.
+            Self::Callback(_) => None,
         }
     }
 }
 
+/// Invoke a callback every time any filtered table is updated.
+struct EmitToCallback {
+    filter: HashSet<String>,
+    callback: Box<dyn Fn() + Send + Sync>,
+}
+
 /// Match table updates against a set of tables to filter again. Emit an empty notification every
 /// time any filtered table is updated.
 struct EmitIfMatches {
@@ -180,6 +233,11 @@ impl TableListenerState {
                     self.notifier.notify(usize::MAX);
                 }
             }
+            ListenerConfigurationInner::Callback(ref callback) => {
+                if !callback.filter.is_disjoint(updates) {
+                    (callback.callback)();
+                }
+            }
         }
     }
 }
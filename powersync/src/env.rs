@@ -1,16 +1,26 @@
+use super::db::connection::{RawSqliteConnection, SqliteConnection};
+use super::db::core_extension::CoreExtensionVersion;
 use super::db::pool::ConnectionPool;
 use crate::error::{PowerSyncError, RawPowerSyncError};
-use crate::http::HttpClient;
+use crate::http::{HttpClient, Request, Response};
 use num_traits::FromPrimitive;
 use powersync_core::powersync_init_static;
 use powersync_sqlite_nostd::ResultCode;
-use std::{pin::Pin, time::Duration};
+use powersync_sqlite_nostd::bindings::{SQLITE_OPEN_CREATE, SQLITE_OPEN_READWRITE};
+use std::{
+    ops::Range,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
 
 /// All external dependencies required for the PowerSync SDK.
 ///
 /// This includes the [HttpClient] used to connect to the PowerSync Service, the [ConnectionPool]
 /// used to run queries against the local SQLite database and a [Timer] implementing an executor-
-/// independent way to delay futures.
+/// independent way to delay futures. A [Clock] reporting the current time can be configured with
+/// [Self::with_clock], and a [Random] source used to add jitter to retry delays with
+/// [Self::with_random].
 pub struct PowerSyncEnvironment {
     /// The [HttpClient] used to connect to the sync service.
     pub(crate) client: Box<dyn HttpClient>,
@@ -18,6 +28,12 @@ pub struct PowerSyncEnvironment {
     pub(crate) pool: ConnectionPool,
     /// The [Timer] implementation used to delay sync iterations after errors.
     pub(crate) timer: &'static (dyn Timer + Send + Sync),
+    /// The [Clock] used to evaluate expiry times in the sync status.
+    pub(crate) clock: &'static (dyn Clock + Send + Sync),
+    /// The [Random] source used to add jitter to delays between sync iterations.
+    pub(crate) random: &'static (dyn Random + Send + Sync),
+    /// Versions of the core extension accepted when opening databases.
+    pub(crate) core_extension_versions: Range<CoreExtensionVersion>,
 }
 
 impl PowerSyncEnvironment {
@@ -30,9 +46,81 @@ impl PowerSyncEnvironment {
             client: Box::new(client),
             pool,
             timer,
+            clock: Self::system_clock(),
+            random: Self::system_random(),
+            core_extension_versions: CoreExtensionVersion::compatible(),
         }
     }
 
+    /// Replaces the [Clock] of this environment, which defaults to [Self::system_clock].
+    ///
+    /// This is mostly useful for tests asserting expiry behavior.
+    pub fn with_clock(mut self, clock: &'static (dyn Clock + Send + Sync)) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replaces the [Random] source of this environment, which defaults to [Self::system_random].
+    ///
+    /// This is mostly useful for tests asserting retry delays, see [SeededRandom].
+    pub fn with_random(mut self, random: &'static (dyn Random + Send + Sync)) -> Self {
+        self.random = random;
+        self
+    }
+
+    /// Replaces the range of core extension versions accepted by databases using this
+    /// environment, which defaults to [CoreExtensionVersion::compatible].
+    ///
+    /// This allows testing against newer versions of the core extension before the SDK has been
+    /// updated to support them. A warning is logged when a version outside of the supported range
+    /// is used.
+    pub fn with_core_extension_versions(mut self, versions: Range<CoreExtensionVersion>) -> Self {
+        self.core_extension_versions = versions;
+        self
+    }
+
+    /// Creates an environment using the given client and pool, with [Self::tokio_timer] as a
+    /// timer.
+    #[cfg(feature = "tokio")]
+    pub fn with_tokio<C: HttpClient>(client: C, pool: ConnectionPool) -> Self {
+        Self::custom(client, pool, Self::tokio_timer())
+    }
+
+    /// Creates an environment using the given client and pool, with [Self::async_io_timer] as a
+    /// timer.
+    #[cfg(feature = "smol")]
+    pub fn with_smol<C: HttpClient>(client: C, pool: ConnectionPool) -> Self {
+        Self::custom(client, pool, Self::async_io_timer())
+    }
+
+    /// Creates an environment for databases that only use local features and never connect to a
+    /// PowerSync service.
+    ///
+    /// The HTTP client used by this environment fails all requests, so calling
+    /// [crate::PowerSyncDatabase::connect] on a database using it will only report errors.
+    /// Delays (e.g. for [crate::PowerSyncDatabase::reader_timeout]) use [Self::async_io_timer]
+    /// when the `smol` feature is enabled. Otherwise, a single background thread is started to
+    /// complete pending delays.
+    pub fn local_only(pool: ConnectionPool) -> Self {
+        struct LocalOnlyClient;
+
+        #[async_trait::async_trait]
+        impl HttpClient for LocalOnlyClient {
+            async fn send(&self, _req: Request) -> Result<Response, PowerSyncError> {
+                Err(PowerSyncError::argument_error(
+                    "Local-only databases can't connect to a PowerSync service",
+                ))
+            }
+        }
+
+        #[cfg(feature = "smol")]
+        let timer = Self::async_io_timer();
+        #[cfg(not(feature = "smol"))]
+        let timer = &thread_timer::THREAD_TIMER;
+
+        Self::custom(LocalOnlyClient, pool, timer)
+    }
+
     /// Calls `sqlite3_auto_extension` with the statically-linked core extension.
     ///
     /// This needs to be invoked before using the PowerSync SDK. It can safely be called multiple
@@ -48,6 +136,46 @@ impl PowerSyncEnvironment {
         }
     }
 
+    /// Checks that the PowerSync core extension is available and that its version is supported,
+    /// returning that version.
+    ///
+    /// This opens a temporary in-memory database, so it can be used to fail early (e.g. when an
+    /// incompatible core extension has been linked) before opening actual databases. The core
+    /// extension needs to be registered first, e.g. with [Self::powersync_auto_extension].
+    pub fn verify_core() -> Result<CoreExtensionVersion, PowerSyncError> {
+        let conn = SqliteConnection::from(RawSqliteConnection::open(
+            c":memory:",
+            SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
+        )?);
+
+        CoreExtensionVersion::check_from_db(&conn, &CoreExtensionVersion::compatible())
+    }
+
+    /// A [Clock] implementation returning [SystemTime::now].
+    pub fn system_clock() -> &'static (dyn Clock + Send + Sync) {
+        struct SystemClock;
+        impl Clock for SystemClock {
+            fn now(&self) -> SystemTime {
+                SystemTime::now()
+            }
+        }
+        &SystemClock
+    }
+
+    /// A [Random] implementation using randomly-seeded hash functions from the standard library.
+    pub fn system_random() -> &'static (dyn Random + Send + Sync) {
+        struct SystemRandom;
+        impl Random for SystemRandom {
+            fn next_u64(&self) -> u64 {
+                use std::hash::{BuildHasher, RandomState};
+
+                RandomState::new().hash_one(SystemTime::now())
+            }
+        }
+
+        &SystemRandom
+    }
+
     /// A [Timer] implementation based on [async_io::Timer].
     #[cfg(feature = "smol")]
     pub fn async_io_timer() -> &'static (dyn Timer + Send + Sync) {
@@ -95,3 +223,230 @@ pub trait Timer {
     /// the context's waker to be woken after the specified `duration`.
     fn delay_once(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
 }
+
+/// The [Timer] used by [PowerSyncEnvironment::local_only] when no async runtime is enabled.
+#[cfg(not(feature = "smol"))]
+mod thread_timer {
+    use super::Timer;
+    use std::{
+        cmp::Reverse,
+        collections::BinaryHeap,
+        pin::Pin,
+        sync::{Condvar, Mutex},
+        time::{Duration, Instant},
+    };
+
+    /// A [Timer] completing delays on a single background thread, which is started on first use.
+    ///
+    /// This works with any executor, but is less efficient than timers integrated into an async
+    /// runtime.
+    pub(super) struct ThreadTimer {
+        pending: Mutex<ThreadTimerState>,
+        changed: Condvar,
+    }
+
+    struct ThreadTimerState {
+        started: bool,
+        next_id: u64,
+        delays: BinaryHeap<Reverse<PendingDelay>>,
+    }
+
+    /// A delay scheduled on the [ThreadTimer], completed by dropping [Self::complete].
+    struct PendingDelay {
+        deadline: Instant,
+        id: u64,
+        complete: async_channel::Sender<()>,
+    }
+
+    impl PendingDelay {
+        fn key(&self) -> (Instant, u64) {
+            (self.deadline, self.id)
+        }
+    }
+
+    impl PartialEq for PendingDelay {
+        fn eq(&self, other: &Self) -> bool {
+            self.key() == other.key()
+        }
+    }
+
+    impl Eq for PendingDelay {}
+
+    impl PartialOrd for PendingDelay {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for PendingDelay {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.key().cmp(&other.key())
+        }
+    }
+
+    pub(super) static THREAD_TIMER: ThreadTimer = ThreadTimer {
+        pending: Mutex::new(ThreadTimerState {
+            started: false,
+            next_id: 0,
+            delays: BinaryHeap::new(),
+        }),
+        changed: Condvar::new(),
+    };
+
+    impl ThreadTimer {
+        fn run(&self) {
+            let mut state = self.pending.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                while state
+                    .delays
+                    .peek()
+                    .is_some_and(|Reverse(delay)| delay.deadline <= now)
+                {
+                    // Dropping the sender completes the pending receive.
+                    state.delays.pop();
+                }
+
+                state = match state.delays.peek() {
+                    Some(Reverse(next)) => {
+                        let timeout = next.deadline - now;
+                        self.changed.wait_timeout(state, timeout).unwrap().0
+                    }
+                    None => self.changed.wait(state).unwrap(),
+                };
+            }
+        }
+    }
+
+    impl Timer for ThreadTimer {
+        fn delay_once(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            let (send, receive) = async_channel::bounded::<()>(1);
+            let Some(deadline) = Instant::now().checked_add(duration) else {
+                // The delay never completes, so there's no need to schedule it.
+                return Box::pin(async move {
+                    let _send = send;
+                    let _ = receive.recv().await;
+                });
+            };
+
+            {
+                let mut state = self.pending.lock().unwrap();
+                if !state.started {
+                    state.started = true;
+                    std::thread::Builder::new()
+                        .name("powersync-timer".into())
+                        .spawn(|| THREAD_TIMER.run())
+                        .expect("Could not start timer thread");
+                }
+
+                // Delays that are no longer awaited don't need to be kept around until they expire.
+                state
+                    .delays
+                    .retain(|Reverse(delay)| !delay.complete.is_closed());
+
+                let id = state.next_id;
+                state.next_id += 1;
+                state.delays.push(Reverse(PendingDelay {
+                    deadline,
+                    id,
+                    complete: send,
+                }));
+            }
+            self.changed.notify_one();
+
+            Box::pin(async move {
+                let _ = receive.recv().await;
+            })
+        }
+    }
+}
+
+/// A source for the current time, as part of the [PowerSyncEnvironment].
+///
+/// The sync status consults this clock to determine whether stream subscriptions have expired.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A source of random numbers, as part of the [PowerSyncEnvironment].
+///
+/// The sync client uses this to add jitter to delays between sync iterations, so that many clients
+/// disconnected at the same time don't reconnect at the same time.
+pub trait Random {
+    /// Returns a uniformly distributed random number.
+    fn next_u64(&self) -> u64;
+}
+
+/// A deterministic [Random] implementation based on the SplitMix64 generator.
+///
+/// Since environments reference [Random] sources statically, this can be used in a `static`:
+///
+/// ```
+/// use powersync::env::SeededRandom;
+///
+/// static RANDOM: SeededRandom = SeededRandom::new(42);
+/// ```
+pub struct SeededRandom {
+    state: AtomicU64,
+}
+
+impl SeededRandom {
+    /// Creates a generator returning a sequence of numbers determined by the `seed`.
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+}
+
+impl Random for SeededRandom {
+    fn next_u64(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9e3779b97f4a7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(all(test, feature = "tokio", feature = "rusqlite"))]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use rusqlite::Connection;
+
+    use super::PowerSyncEnvironment;
+    use crate::{
+        ConnectionPool,
+        error::PowerSyncError,
+        http::{HttpClient, Request, Response},
+    };
+
+    struct UnreachableClient;
+
+    #[async_trait]
+    impl HttpClient for UnreachableClient {
+        async fn send(&self, _req: Request) -> Result<Response, PowerSyncError> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn with_tokio_timer() {
+        let conn = Connection::open_in_memory().unwrap();
+        let env = PowerSyncEnvironment::with_tokio(
+            UnreachableClient,
+            ConnectionPool::single_connection(conn),
+        );
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(env.timer.delay_once(Duration::from_millis(1)));
+    }
+}
@@ -20,6 +20,46 @@ impl PowerSyncError {
     pub(crate) fn argument_error(desc: impl Into<Cow<'static, str>>) -> Self {
         RawPowerSyncError::ArgumentError { desc: desc.into() }.into()
     }
+
+    /// A coarse classification of this error, e.g. to decide whether to retry an operation.
+    pub fn kind(&self) -> PowerSyncErrorKind {
+        use RawPowerSyncError::*;
+
+        match &*self.inner {
+            ArgumentError { .. } => PowerSyncErrorKind::InvalidArgument,
+            ConnectorCredentials { .. } | InvalidPowerSyncEndpoint { .. } => {
+                PowerSyncErrorKind::ConnectorCredentials
+            }
+            InvalidCredentials => PowerSyncErrorKind::InvalidCredentials,
+            Cancelled => PowerSyncErrorKind::Cancelled,
+            IO { .. }
+            | UnexpectedStatusCode { .. }
+            | ConnectTimeout
+            | IdleTimeout
+            | SyncServiceResponseParsing { .. } => PowerSyncErrorKind::Network,
+            #[cfg(feature = "reqwest")]
+            Reqwest { .. } => PowerSyncErrorKind::Network,
+            _ => PowerSyncErrorKind::Other,
+        }
+    }
+}
+
+/// A classification of [PowerSyncError]s, see [PowerSyncError::kind].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PowerSyncErrorKind {
+    /// An invalid argument was passed to the SDK.
+    InvalidArgument,
+    /// The [crate::BackendConnector] failed to fetch credentials, or returned an invalid endpoint.
+    ConnectorCredentials,
+    /// The PowerSync service rejected credentials returned by the connector.
+    InvalidCredentials,
+    /// Connecting to the PowerSync service failed, or the connection was interrupted.
+    Network,
+    /// The operation has been cancelled through a [crate::CancellationToken].
+    Cancelled,
+    /// Other errors, e.g. from SQLite.
+    Other,
 }
 
 #[cfg(feature = "rusqlite")]
@@ -56,7 +96,11 @@ impl Display for PowerSyncError {
     }
 }
 
-impl Error for PowerSyncError {}
+impl Error for PowerSyncError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner.source()
+    }
+}
 
 /// A structured enumeration of possible errors that can occur in the core extension.
 #[derive(Error, Debug)]
@@ -106,8 +150,35 @@ pub(crate) enum RawPowerSyncError {
     },
     #[error("The PowerSync service did not accept credentials returned by connector")]
     InvalidCredentials,
-    #[error("Unexpected HTTP status code from PowerSync service: {code}")]
-    UnexpectedStatusCode { code: u16 },
+    /// An operation has been cancelled through a [crate::CancellationToken].
+    #[error("The operation has been cancelled")]
+    Cancelled,
+    /// [crate::BackendConnector::fetch_credentials] returned an error.
+    #[error("Connector could not fetch credentials: {source}")]
+    ConnectorCredentials { source: PowerSyncError },
+    /// The PowerSync service responded with an unexpected status code. The `body` contains the
+    /// start of the response, which typically describes the error.
+    #[error(
+        "Unexpected HTTP status code from PowerSync service: {code}{}",
+        .body.as_deref().map(|body| format!(", {body}")).unwrap_or_default()
+    )]
+    UnexpectedStatusCode { code: u16, body: Option<String> },
+    /// The PowerSync service didn't send a sync line within the configured connect timeout.
+    #[error("Timed out waiting for the PowerSync service to respond")]
+    ConnectTimeout,
+    /// An established sync stream didn't receive a line within the configured idle timeout.
+    #[error("The sync stream has been idle for too long")]
+    IdleTimeout,
+    /// No connection could be leased from the pool within the requested timeout.
+    #[error("Timed out waiting for a {kind} connection")]
+    LeaseTimeout { kind: &'static str },
+    /// The sync client disconnected before completing the sync awaited by
+    /// [crate::PowerSyncDatabase::connect_and_wait].
+    #[error("Disconnected before the sync completed")]
+    DisconnectedBeforeSync,
+    /// The database has been closed with [crate::PowerSyncDatabase::close].
+    #[error("The database has been closed")]
+    DatabaseClosed,
 }
 
 impl From<ResultCode> for PowerSyncError {
@@ -1,4 +1,5 @@
 use crate::error::PowerSyncError;
+use crate::util::{BsonObjects, LineSplitter};
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures_lite::{Stream, StreamExt};
@@ -63,6 +64,27 @@ impl ResponseBody {
 
         Ok(body)
     }
+
+    /// Reads at most `limit` bytes from the response, discarding the rest.
+    /// Appends chunks of this response to `body` until it contains at least `limit` bytes or the
+    /// response ends.
+    ///
+    /// Chunks are appended as they arrive, so callers can use a partial body if this future is
+    /// dropped. The body may exceed `limit` and should be truncated by callers.
+    pub(crate) async fn read_up_to(
+        mut self,
+        limit: usize,
+        body: &mut Vec<u8>,
+    ) -> Result<(), PowerSyncError> {
+        while body.len() < limit {
+            let Some(chunk) = self.reader.try_next().await? else {
+                break;
+            };
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(feature = "reqwest")]
@@ -126,3 +148,82 @@ impl<C: HttpClient> HttpClient for Arc<C> {
         inner.send(req).await
     }
 }
+
+/// A sync line received from the PowerSync service, as split by [parse_sync_lines].
+///
+/// The sync client passes each line to the core extension as-is, so this is all a custom
+/// transport needs to produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncLine {
+    /// A JSON sync line from an `application/x-ndjson` response.
+    Text(String),
+    /// A BSON document from an `application/vnd.powersync.bson-stream` response.
+    Binary(Vec<u8>),
+}
+
+/// Splits a streamed sync response body into individual sync lines.
+///
+/// For BSON responses (detected from the `content_type`), this tracks the length prefix to split
+/// at objects. Otherwise, the response is treated as newline-delimited JSON. This is useful for
+/// custom transports that need to reuse the framing used by the PowerSync service.
+pub fn parse_sync_lines(
+    reader: ResponseStream,
+    content_type: Option<&str>,
+) -> impl Stream<Item = Result<SyncLine, PowerSyncError>> + Send + 'static {
+    let is_bson = match content_type {
+        None => false,
+        Some(value) => value.contains("vnd.powersync.bson-stream"),
+    };
+
+    if is_bson {
+        BsonObjects::new(reader)
+            .map(|line| line.map(SyncLine::Binary))
+            .boxed()
+    } else {
+        LineSplitter::from(reader)
+            .map(|line| line.map(SyncLine::Text))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use futures_lite::{StreamExt, future, stream};
+
+    use super::{SyncLine, parse_sync_lines};
+
+    fn lines(source: &'static [u8], content_type: Option<&str>) -> Vec<SyncLine> {
+        let reader = stream::once(Ok(Bytes::from_static(source))).boxed();
+        future::block_on(async {
+            parse_sync_lines(reader, content_type)
+                .map(|line| line.unwrap())
+                .collect()
+                .await
+        })
+    }
+
+    #[test]
+    fn splits_ndjson() {
+        let lines = lines(b"{}\n{}\n", Some("application/x-ndjson"));
+        assert_eq!(
+            lines,
+            vec![SyncLine::Text("{}".into()), SyncLine::Text("{}".into())]
+        );
+    }
+
+    #[test]
+    fn splits_bson() {
+        let lines = lines(
+            &[5, 0, 0, 0, 0, 5, 0, 0, 0, 0],
+            Some("application/vnd.powersync.bson-stream"),
+        );
+        assert_eq!(
+            lines,
+            vec![
+                SyncLine::Binary(vec![5, 0, 0, 0, 0]),
+                SyncLine::Binary(vec![5, 0, 0, 0, 0])
+            ]
+        );
+    }
+}
@@ -3,18 +3,29 @@ pub mod env;
 mod sync;
 mod util;
 
-pub use db::PowerSyncDatabase;
-pub use db::crud::{CrudEntry, CrudTransaction, UpdateType};
+pub use db::core_extension::CoreExtensionVersion;
+pub use db::crud::{CrudEntry, CrudTransaction, ExecResult, UpdateType};
 #[cfg(feature = "ffi")]
 pub use db::internal::InnerPowerSyncState;
-pub use db::pool::{ConnectionPool, LeasedConnection};
+pub use db::pool::{ConnectionPool, LeasedConnection, PoolOptions, SqliteUpdateNotification};
 pub use db::streams::StreamSubscription;
 pub use db::streams::StreamSubscriptionOptions;
 pub use db::streams::SyncStream;
-pub use sync::connector::{BackendConnector, PowerSyncCredentials};
-pub use sync::options::SyncOptions;
-pub use sync::status::SyncStatusData;
+pub use db::watch::TableChangeListener;
+pub use db::{PowerSyncDatabase, QueryPage};
+pub use sync::connector::{
+    BackendConnector, PowerSyncCredentials, TransactionConnector, TransactionUploader,
+};
+pub use sync::instruction::{
+    ActiveStreamSubscription, BucketProgress, DownloadProgress, DownloadSyncStatus, StreamError,
+    SyncPriorityStatus, Timestamp,
+};
+pub use sync::observer::SyncObserver;
+pub use sync::options::{RetryPolicy, SyncOptions};
+pub use sync::progress::ProgressCounters;
+pub use sync::status::{ConnectionState, StatusChangeListener, SyncStatusData};
 pub use sync::stream_priority::StreamPriority;
+pub use util::{CancellationToken, SerializedJsonObject};
 pub mod error;
 pub mod http;
 
@@ -1,33 +1,131 @@
+use std::time::SystemTime;
+
 use async_trait::async_trait;
 use url::Url;
 
 use crate::error::{PowerSyncError, RawPowerSyncError};
+use crate::{CrudEntry, CrudTransaction, PowerSyncDatabase};
 
 /// A backend connector is responsible for upload local writes as well as resolving JWTs used to
 /// connect to the PowerSync service.
 #[async_trait]
 pub trait BackendConnector: Send + Sync {
     /// Fetches a fresh JWT from the backend to be used against the PowerSync service.
+    ///
+    /// Credentials are cached until they expire (see [PowerSyncCredentials::expires_at]). Each
+    /// sync iteration and upload uses the endpoint of the credentials it obtained, so connectors
+    /// can return a different endpoint (e.g. to route clients to another region) which is used
+    /// after the next reconnect.
     async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError>;
 
     /// Inspects completed CRUD transactions on a database and uploads them.
     async fn upload_data(&self) -> Result<(), PowerSyncError>;
+
+    /// Called when [Self::upload_data] completed without removing `entry` from the upload queue.
+    ///
+    /// This typically indicates that the connector doesn't call [CrudTransaction::complete]. The
+    /// SDK delays the next upload attempt afterwards. This method can be used to log or otherwise
+    /// inspect the affected entry.
+    fn on_stuck_crud_item(&self, entry: &CrudEntry) {
+        let _ = entry;
+    }
+}
+
+/// A simpler alternative to implementing [BackendConnector] for connectors that upload local writes
+/// one transaction at a time.
+///
+/// Use [TransactionConnector] to obtain a [BackendConnector] from this.
+#[async_trait]
+pub trait TransactionUploader: Send + Sync {
+    /// Fetches a fresh JWT from the backend, see [BackendConnector::fetch_credentials].
+    async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError>;
+
+    /// Uploads all writes of a single transaction to the backend.
+    ///
+    /// The transaction is marked as completed after this method returns successfully.
+    async fn upload_transaction(
+        &self,
+        transaction: &CrudTransaction<'_>,
+    ) -> Result<(), PowerSyncError>;
+}
+
+/// A [BackendConnector] passing each pending [CrudTransaction] to a [TransactionUploader].
+pub struct TransactionConnector<T> {
+    db: PowerSyncDatabase,
+    uploader: T,
+}
+
+impl<T: TransactionUploader> TransactionConnector<T> {
+    /// Creates a connector uploading transactions made on `db` with the `uploader`.
+    pub fn new(db: PowerSyncDatabase, uploader: T) -> Self {
+        Self { db, uploader }
+    }
+}
+
+#[async_trait]
+impl<T: TransactionUploader> BackendConnector for TransactionConnector<T> {
+    async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+        self.uploader.fetch_credentials().await
+    }
+
+    async fn upload_data(&self) -> Result<(), PowerSyncError> {
+        while let Some(transaction) = self.db.next_crud_transaction().await? {
+            self.uploader.upload_transaction(&transaction).await?;
+            transaction.complete().await?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Credentials used to connect to a PowerSync service instance.
+#[derive(Clone)]
 pub struct PowerSyncCredentials {
     /// PowerSync endpoint, e.g. `https://myinstance.powersync.co`.
     pub endpoint: String,
     /// The token used to authenticate against the PowerSync service.
     pub token: String,
+    /// When the [Self::token] expires.
+    ///
+    /// If set, the SDK reuses these credentials until they expire instead of calling
+    /// [BackendConnector::fetch_credentials] for each connection attempt and write checkpoint.
+    pub expires_at: Option<SystemTime>,
 }
 
 impl PowerSyncCredentials {
-    /// Parses the [Self::endpoint] into a URI.
-    pub(crate) fn parsed_endpoint(&self, endpoint: &str) -> Result<Url, PowerSyncError> {
+    /// Checks that [Self::endpoint] is a valid `http` or `https` URL.
+    ///
+    /// The SDK validates endpoints when connecting as well, but calling this in
+    /// [BackendConnector::fetch_credentials] surfaces errors before a sync iteration starts.
+    pub fn validate(&self) -> Result<(), PowerSyncError> {
+        self.base_url().map(|_| ())
+    }
+
+    fn base_url(&self) -> Result<Url, PowerSyncError> {
         let url = Url::parse(&self.endpoint)
             .map_err(|e| RawPowerSyncError::InvalidPowerSyncEndpoint { inner: e })?;
 
+        if url.cannot_be_a_base() {
+            return Err(PowerSyncError::argument_error(format!(
+                "URL {} must be a valid base URL",
+                self.endpoint
+            )));
+        }
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(PowerSyncError::argument_error(format!(
+                "URL {} must use http or https",
+                self.endpoint
+            )));
+        }
+
+        Ok(url)
+    }
+
+    /// Parses the [Self::endpoint] into a URI.
+    pub(crate) fn parsed_endpoint(&self, endpoint: &str) -> Result<Url, PowerSyncError> {
+        let url = self.base_url()?;
+
         url.join(endpoint).map_err(|_| {
             PowerSyncError::argument_error(format!(
                 "URL {} must be a valid base URL",
@@ -41,13 +139,16 @@ impl PowerSyncCredentials {
 mod test {
     use crate::PowerSyncCredentials;
 
-    fn is_endpoint_valid(endpoint: &str) -> bool {
+    fn credentials(endpoint: &str) -> PowerSyncCredentials {
         PowerSyncCredentials {
             token: "".to_string(),
             endpoint: endpoint.to_string(),
+            expires_at: None,
         }
-        .parsed_endpoint("")
-        .is_ok()
+    }
+
+    fn is_endpoint_valid(endpoint: &str) -> bool {
+        credentials(endpoint).parsed_endpoint("").is_ok()
     }
 
     #[test]
@@ -58,4 +159,26 @@ mod test {
         assert!(is_endpoint_valid("http://localhost:8080/"));
         assert!(is_endpoint_valid("http://localhost:8080/powersync"));
     }
+
+    #[test]
+    fn validate() {
+        assert!(
+            credentials("https://powersync.example.com")
+                .validate()
+                .is_ok()
+        );
+
+        assert!(credentials("not a url").validate().is_err());
+        assert!(credentials("localhost:8080").validate().is_err());
+        assert!(
+            credentials("ftp://powersync.example.com")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            credentials("mailto:powersync@example.com")
+                .validate()
+                .is_err()
+        );
+    }
 }
@@ -1,11 +1,16 @@
-use std::sync::RwLock;
+use std::sync::{
+    Arc, RwLock, Weak,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 
 use async_channel::{Receiver, Sender};
 use async_oneshot::oneshot;
+use event_listener::Event;
 
 use crate::{
     SyncOptions,
     db::internal::InnerPowerSyncState,
+    error::{PowerSyncError, RawPowerSyncError},
     sync::{
         download::DownloadActorCommand, streams::ChangedSyncSubscriptions,
         upload::UploadActorCommand,
@@ -39,39 +44,55 @@ impl<T> AsyncRequest<T> {
 pub struct SyncCoordinator {
     control_downloads: RwLock<Option<Sender<AsyncRequest<DownloadActorCommand>>>>,
     control_uploads: RwLock<Option<Sender<AsyncRequest<UploadActorCommand>>>>,
+    /// The amount of actors that have been installed and haven't completed yet.
+    running_actors: AtomicUsize,
+    /// Notified when an actor completes.
+    actor_stopped: Event,
+    /// Whether [Self::close] has been called, after which requests to actors are ignored.
+    closed: AtomicBool,
 }
 
 impl SyncCoordinator {
     pub async fn connect(&self, options: SyncOptions, db: &InnerPowerSyncState) {
         {
-            let mut lock = db.retry_delay.lock().unwrap();
-            *lock = Some(options.retry_delay);
+            let mut lock = db.retry_policy.lock().unwrap();
+            *lock = Some(options.retry_policy);
+        }
+        {
+            let mut lock = db.last_options.lock().unwrap();
+            *lock = Some(options.clone());
         }
 
-        let connector = options.connector.clone();
-        self.download_actor_request(DownloadActorCommand::Connect(options))
+        // Requests are ignored after the database has been closed.
+        let _ = self
+            .download_actor_request(DownloadActorCommand::Connect(options.clone()))
             .await;
-        self.upload_actor_request(UploadActorCommand::Connect(connector))
+        let _ = self
+            .upload_actor_request(UploadActorCommand::Connect(options))
             .await;
     }
 
     pub async fn disconnect(&self) {
-        self.download_actor_request(DownloadActorCommand::Disconnect)
+        let _ = self
+            .download_actor_request(DownloadActorCommand::Disconnect)
             .await;
-        self.upload_actor_request(UploadActorCommand::Disconnect)
+        let _ = self
+            .upload_actor_request(UploadActorCommand::Disconnect)
             .await;
     }
 
     /// Requests a round of CRUD uploads.
     pub async fn trigger_crud_uploads(&self) {
-        self.upload_actor_request(UploadActorCommand::TriggerCrudUpload)
+        let _ = self
+            .upload_actor_request(UploadActorCommand::TriggerCrudUpload)
             .await;
     }
 
     /// Marks CRUD uploads as complete, allowing the download client to retry if a previous
     /// checkpoint was blocked by pending uploads.
     pub async fn mark_crud_uploads_completed(&self) {
-        self.download_actor_request(DownloadActorCommand::CrudUploadComplete)
+        let _ = self
+            .download_actor_request(DownloadActorCommand::CrudUploadComplete)
             .await;
     }
 
@@ -81,19 +102,78 @@ impl SyncCoordinator {
     /// disconnected.
     /// This is a no-op while connected.
     pub async fn resolve_offline_sync_status(&self) {
-        self.download_actor_request(DownloadActorCommand::ResolveOfflineSyncStatusIfNotConnected)
+        let _ = self
+            .download_actor_request(DownloadActorCommand::ResolveOfflineSyncStatusIfNotConnected)
             .await;
     }
 
     /// Handle the set of active sync stream subscriptions changing.
     ///
-    /// This is a no-op if not connected.
-    pub async fn handle_subscriptions_changed(&self, update: ChangedSyncSubscriptions) {
+    /// This is a no-op if not connected, and fails if the database has been closed.
+    pub async fn handle_subscriptions_changed(
+        &self,
+        update: ChangedSyncSubscriptions,
+    ) -> Result<(), PowerSyncError> {
         self.download_actor_request(DownloadActorCommand::SubscriptionsChanged(update))
-            .await;
+            .await
+    }
+
+    /// Asks the download actor for the sync request it would send with the given `options`.
+    ///
+    /// This fails while the sync client is connected, since resolving the request would reset its
+    /// state in the core extension.
+    pub async fn resolve_sync_request(
+        &self,
+        options: SyncOptions,
+    ) -> Result<String, PowerSyncError> {
+        let (result, receive_result) = oneshot();
+        self.download_actor_request(DownloadActorCommand::ResolveSyncRequest { options, result })
+            .await?;
+
+        receive_result
+            .await
+            .map_err(|_| RawPowerSyncError::DatabaseClosed.into())
+    }
+
+    /// Disconnects and stops the upload and download actors, completing once both actors have
+    /// completed.
+    ///
+    /// Further requests to actors are ignored after calling this.
+    pub async fn close(&self) {
+        let has_actors = self.control_downloads.read().unwrap().is_some();
+        // Mark the coordinator as closed first, so that requests to actors whose futures have
+        // been dropped fail instead of panicking.
+        let was_closed = self.closed.swap(true, Ordering::SeqCst);
+        if has_actors && !was_closed {
+            self.disconnect().await;
+        }
+
+        // Actors stop once their command channel is closed.
+        self.control_downloads.write().unwrap().take();
+        self.control_uploads.write().unwrap().take();
+
+        loop {
+            if self.running_actors.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+
+            let listener = self.actor_stopped.listen();
+            if self.running_actors.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /// Called when the future of an actor installed through this coordinator has completed or
+    /// has been dropped, see [ActorStoppedGuard].
+    fn mark_actor_stopped(&self) {
+        self.running_actors.fetch_sub(1, Ordering::SeqCst);
+        self.actor_stopped.notify(usize::MAX);
     }
 
     fn install_actor_channel<T>(
+        &self,
         slot: &RwLock<Option<Sender<AsyncRequest<T>>>>,
     ) -> Receiver<AsyncRequest<T>> {
         let mut slot = slot.write().unwrap();
@@ -103,48 +183,102 @@ impl SyncCoordinator {
         }
 
         let (send, receive) = async_channel::bounded(1);
-        *slot = Some(send);
+        if self.closed.load(Ordering::SeqCst) {
+            // Actors installed after closing the database stop immediately.
+            drop(send);
+        } else {
+            *slot = Some(send);
+        }
+        self.running_actors.fetch_add(1, Ordering::SeqCst);
         receive
     }
 
+    /// Returns the channel to send commands to an actor, or [None] if [Self::close] has been
+    /// called.
     fn obtain_channel<T>(
+        &self,
         slot: &RwLock<Option<Sender<AsyncRequest<T>>>>,
-    ) -> Sender<AsyncRequest<T>> {
+    ) -> Option<Sender<AsyncRequest<T>>> {
         let slot = slot.read().unwrap();
-        let Some(slot) = &*slot else {
-            panic!("Actor has not been registered");
-        };
-
-        slot.clone()
+        match &*slot {
+            Some(sender) => Some(sender.clone()),
+            None if self.closed.load(Ordering::SeqCst) => None,
+            None => {
+                drop(slot);
+                panic!("Actor has not been registered")
+            }
+        }
     }
 
     pub fn receive_download_commands(&self) -> Receiver<AsyncRequest<DownloadActorCommand>> {
-        Self::install_actor_channel(&self.control_downloads)
+        self.install_actor_channel(&self.control_downloads)
     }
 
     pub fn receive_upload_commands(&self) -> Receiver<AsyncRequest<UploadActorCommand>> {
-        Self::install_actor_channel(&self.control_uploads)
+        self.install_actor_channel(&self.control_uploads)
     }
 
-    async fn download_actor_request(&self, cmd: DownloadActorCommand) {
-        let downloads = Self::obtain_channel(&self.control_downloads);
+    async fn download_actor_request(
+        &self,
+        cmd: DownloadActorCommand,
+    ) -> Result<(), PowerSyncError> {
+        let downloads = self
+            .obtain_channel(&self.control_downloads)
+            .ok_or(RawPowerSyncError::DatabaseClosed)?;
 
         let (request, response) = AsyncRequest::new(cmd);
-        downloads
-            .send(request)
-            .await
-            .expect("Download actor not running, start it with download_actor()");
+        if downloads.send(request).await.is_err() {
+            assert!(
+                self.closed.load(Ordering::SeqCst),
+                "Download actor not running, start it with download_actor()"
+            );
+            return Err(RawPowerSyncError::DatabaseClosed.into());
+        }
+        // Actors may also acknowledge commands by dropping the response after handling them.
         let _ = response.await;
+        Ok(())
     }
 
-    async fn upload_actor_request(&self, cmd: UploadActorCommand) {
-        let uploads = Self::obtain_channel(&self.control_uploads);
+    async fn upload_actor_request(&self, cmd: UploadActorCommand) -> Result<(), PowerSyncError> {
+        let uploads = self
+            .obtain_channel(&self.control_uploads)
+            .ok_or(RawPowerSyncError::DatabaseClosed)?;
 
         let (request, response) = AsyncRequest::new(cmd);
-        uploads
-            .send(request)
-            .await
-            .expect("Upload actor not running, start it with upload_actor()");
+        if uploads.send(request).await.is_err() {
+            assert!(
+                self.closed.load(Ordering::SeqCst),
+                "Upload actor not running, start it with upload_actor()"
+            );
+            return Err(RawPowerSyncError::DatabaseClosed.into());
+        }
+        // Actors may also acknowledge commands by dropping the response after handling them.
         let _ = response.await;
+        Ok(())
+    }
+}
+
+/// Marks an actor installed through a [SyncCoordinator] as stopped when dropped.
+///
+/// This is owned by the future running the actor, so that [SyncCoordinator::close] also completes
+/// if that future is dropped (e.g. because the executor shuts down) instead of running to
+/// completion.
+pub struct ActorStoppedGuard {
+    coordinator: Weak<SyncCoordinator>,
+}
+
+impl ActorStoppedGuard {
+    pub fn new(coordinator: &Arc<SyncCoordinator>) -> Self {
+        Self {
+            coordinator: Arc::downgrade(coordinator),
+        }
+    }
+}
+
+impl Drop for ActorStoppedGuard {
+    fn drop(&mut self) {
+        if let Some(coordinator) = self.coordinator.upgrade() {
+            coordinator.mark_actor_stopped();
+        }
     }
 }
@@ -4,8 +4,7 @@ use futures_lite::{
     FutureExt,
     future::{self, Boxed},
 };
-use log::warn;
-use serde_json::Map;
+use log::{debug, warn};
 
 use crate::sync::coordinator::SyncCoordinator;
 use crate::{
@@ -14,7 +13,9 @@ use crate::{
     error::PowerSyncError,
     sync::{
         coordinator::AsyncRequest,
-        download::sync_iteration::{DownloadClient, DownloadEvent, StartDownloadIteration},
+        download::sync_iteration::{
+            DownloadClient, DownloadEvent, StartDownloadIteration, resolve_sync_request,
+        },
         instruction::CloseSyncStream,
         streams::ChangedSyncSubscriptions,
     },
@@ -27,6 +28,15 @@ pub enum DownloadActorCommand {
     ResolveOfflineSyncStatusIfNotConnected,
     SubscriptionsChanged(ChangedSyncSubscriptions),
     CrudUploadComplete,
+    /// Resolves the sync request that would be sent with the given options, see
+    /// [resolve_sync_request].
+    ///
+    /// This is handled by the actor so that it can't interfere with a sync iteration, which uses
+    /// the same sync client state in the core extension.
+    ResolveSyncRequest {
+        options: SyncOptions,
+        result: async_oneshot::Sender<Result<String, PowerSyncError>>,
+    },
 }
 
 pub struct DownloadActor {
@@ -34,6 +44,8 @@ pub struct DownloadActor {
     commands: async_channel::Receiver<AsyncRequest<DownloadActorCommand>>,
     db: Arc<InnerPowerSyncState>,
     options: Option<SyncOptions>,
+    /// The amount of sync iterations that have failed since the last successful one.
+    failed_attempts: usize,
 }
 
 impl DownloadActor {
@@ -45,6 +57,7 @@ impl DownloadActor {
             commands,
             db,
             options: None,
+            failed_attempts: 0,
         }
     }
 
@@ -56,15 +69,20 @@ impl DownloadActor {
 
     fn start_iteration(&mut self, options: SyncOptions) {
         let (send_events, receive_event) = async_channel::bounded(1);
-        let start = StartDownloadIteration {
-            parameters: serde_json::Value::Object(Map::new()),
-            schema: self.db.schema.clone(),
-            include_defaults: options.include_default_streams,
-            active_streams: self.db.current_streams.collect_active_streams(),
-        };
-        let future = DownloadClient::new(self.db.clone(), receive_event)
-            .run(options)
-            .boxed();
+        let start = StartDownloadIteration::new(&self.db, &options);
+        let attempt = self.failed_attempts + 1;
+        debug!("Starting sync iteration, attempt {attempt}");
+        if let Some(observer) = options.observer() {
+            observer.on_iteration_start();
+        }
+
+        let future = DownloadClient::new(self.db.clone(), receive_event).run(options);
+        #[cfg(feature = "tracing")]
+        let future = tracing::Instrument::instrument(
+            future,
+            tracing::info_span!("sync_iteration", attempt, endpoint = tracing::field::Empty),
+        );
+        let future = future.boxed();
         send_events
             .try_send(DownloadEvent::Start(start))
             .expect("should send start message");
@@ -88,22 +106,22 @@ impl DownloadActor {
                 match command.command {
                     DownloadActorCommand::Connect(options) => {
                         self.options = Some(options.clone());
+                        self.failed_attempts = 0;
+                        self.db.status.update(|status| {
+                            status.set_did_complete_sync(false);
+                            status.set_fatal_error(None);
+                        });
                         self.start_iteration(options);
                         let _ = command.response.send(());
                     }
                     DownloadActorCommand::ResolveOfflineSyncStatusIfNotConnected => {
-                        let res = async {
-                            let writer = self.db.writer().await?;
-                            self.db
-                                .status
-                                .update(|s| s.resolve_offline_state(writer.sqlite_connection()))?;
-
-                            Ok::<(), PowerSyncError>(())
-                        }
-                        .await;
-                        if let Err(e) = res {
-                            warn!("Could not resolve offline sync state: {e}")
-                        }
+                        self.resolve_offline_sync_status().await;
+                    }
+                    DownloadActorCommand::ResolveSyncRequest {
+                        options,
+                        mut result,
+                    } => {
+                        let _ = result.send(resolve_sync_request(&self.db, &options).await);
                     }
                     DownloadActorCommand::Disconnect
                     | DownloadActorCommand::SubscriptionsChanged(_)
@@ -121,6 +139,8 @@ impl DownloadActor {
                 // So we have to listen for both.
                 enum Event {
                     ForwardedMessage,
+                    DisconnectRequested(async_oneshot::Sender<()>),
+                    Reconnect(SyncOptions),
                     SyncIterationComplete(CloseSyncStream),
                     SyncIterationError(PowerSyncError),
                 }
@@ -128,9 +148,16 @@ impl DownloadActor {
                 let forwarding_request = async {
                     match self.commands.recv().await {
                         Ok(command) => match command.command {
-                            DownloadActorCommand::Connect(_) => {
-                                // We're already connected, do nothing.
-                                // TODO: Compare options and potentially reconnect
+                            DownloadActorCommand::Connect(options) => {
+                                let unchanged = self
+                                    .options
+                                    .as_ref()
+                                    .is_some_and(|current| current.is_equivalent(&options));
+                                if !unchanged {
+                                    let _ = send_events.send(DownloadEvent::Stop).await;
+                                    return Event::Reconnect(options);
+                                }
+                                // Otherwise, we're already connected with these options.
                             }
                             DownloadActorCommand::ResolveOfflineSyncStatusIfNotConnected => {
                                 // We're connected, so nothing we'd have to do.
@@ -143,8 +170,12 @@ impl DownloadActor {
                             DownloadActorCommand::CrudUploadComplete => {
                                 let _ = send_events.send(DownloadEvent::CompletedUpload).await;
                             }
+                            DownloadActorCommand::ResolveSyncRequest { mut result, .. } => {
+                                let _ = result.send(Err(Self::connected_error()));
+                            }
                             DownloadActorCommand::Disconnect => {
                                 let _ = send_events.send(DownloadEvent::Stop).await;
+                                return Event::DisconnectRequested(command.response);
                             }
                         },
                         Err(_) => {
@@ -172,22 +203,72 @@ impl DownloadActor {
                     Event::ForwardedMessage => {
                         // Message was handled, we can go on immediately.
                     }
+                    Event::DisconnectRequested(mut response) => {
+                        // Let the iteration close the stream, but don't reconnect afterwards.
+                        if let Err(e) = iteration.await {
+                            debug!("Sync iteration failed while disconnecting, {e}");
+                        }
+
+                        self.state = DownloadActorState::Idle;
+                        self.resolve_offline_sync_status().await;
+                        let _ = response.send(());
+                    }
+                    Event::Reconnect(options) => {
+                        // Close the current iteration and immediately start a new one with the
+                        // changed options.
+                        if let Err(e) = iteration.await {
+                            debug!("Sync iteration failed while reconnecting, {e}");
+                        }
+
+                        self.options = Some(options.clone());
+                        self.failed_attempts = 0;
+                        self.db.status.update(|status| {
+                            status.set_did_complete_sync(false);
+                            status.set_fatal_error(None);
+                        });
+                        self.start_iteration(options);
+                    }
                     Event::SyncIterationComplete(close) => {
+                        self.failed_attempts = 0;
                         let timeout = if close.hide_disconnect {
                             async {}.boxed()
                         } else {
                             let db = self.db.clone();
 
-                            async move { db.sync_iteration_delay().await }.boxed()
+                            async move { db.sync_iteration_delay(1).await }.boxed()
                         };
 
                         self.state = DownloadActorState::WaitingForReconnect { timeout }
                     }
                     Event::SyncIterationError(e) => {
+                        self.failed_attempts += 1;
+                        if let Some(observer) =
+                            self.options.as_ref().and_then(|options| options.observer())
+                        {
+                            observer.on_error(&e);
+                        }
+
+                        let retry_policy = self
+                            .options
+                            .as_ref()
+                            .map(|options| options.retry_policy)
+                            .unwrap_or_default();
+                        if retry_policy.is_exhausted(self.failed_attempts) {
+                            warn!("Sync failed {} times, not retrying", self.failed_attempts);
+                            self.state = DownloadActorState::Idle;
+                            self.resolve_offline_sync_status().await;
+                            self.db.status.update(|status| {
+                                status.set_download_error(e.clone());
+                                status.set_fatal_error(Some(e));
+                            });
+                            return;
+                        }
+
                         self.db.status.update(|status| status.set_download_error(e));
                         let db = self.db.clone();
+                        let attempt = self.failed_attempts;
                         self.state = DownloadActorState::WaitingForReconnect {
-                            timeout: async move { db.sync_iteration_delay().await }.boxed(),
+                            timeout: async move { db.sync_iteration_delay(attempt).await }.boxed(),
                         }
                     }
                 }
@@ -214,6 +295,7 @@ impl DownloadActor {
                 match future::race(disconnect_requested, timeout_expired).await {
                     Event::DisconnectRequested => {
                         self.state = DownloadActorState::Idle;
+                        self.resolve_offline_sync_status().await;
                     }
                     Event::TimeoutExpired => {
                         self.start_iteration(self.options.as_ref().unwrap().clone());
@@ -224,6 +306,33 @@ impl DownloadActor {
         };
     }
 
+    /// Replaces the reported download status with the offline status from the database.
+    ///
+    /// This is used while not connected, so that the status doesn't report progress from an
+    /// iteration that has already ended.
+    async fn resolve_offline_sync_status(&self) {
+        let res = async {
+            let writer = self.db.writer().await?;
+            self.db.status.update(|s| {
+                s.resolve_offline_state(writer.sqlite_connection(), self.db.env.clock)
+            })?;
+
+            Ok::<(), PowerSyncError>(())
+        }
+        .await;
+        if let Err(e) = res {
+            warn!("Could not resolve offline sync state: {e}")
+        }
+    }
+
+    /// The error returned for [DownloadActorCommand::ResolveSyncRequest] while the sync client is
+    /// connected or waiting to reconnect.
+    fn connected_error() -> PowerSyncError {
+        PowerSyncError::argument_error(
+            "test_connection() can't be used while the database is connected",
+        )
+    }
+
     /// Polls on the given channel until we receive a command indicating that the actor should
     /// disconnect.
     async fn wait_for_disconnect_request(
@@ -238,6 +347,9 @@ impl DownloadActor {
                     | DownloadActorCommand::CrudUploadComplete => {
                         continue;
                     }
+                    DownloadActorCommand::ResolveSyncRequest { mut result, .. } => {
+                        let _ = result.send(Err(Self::connected_error()));
+                    }
                     DownloadActorCommand::Disconnect => {
                         return;
                     }
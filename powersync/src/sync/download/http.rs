@@ -1,37 +1,73 @@
 use std::borrow::Cow;
+use std::pin::pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::http::{Request, Response};
-use crate::util::LineSplitter;
+use crate::http::{Request, Response, parse_sync_lines};
 use crate::{
+    SyncOptions,
     db::internal::InnerPowerSyncState,
     error::{PowerSyncError, RawPowerSyncError},
     sync::{connector::PowerSyncCredentials, download::sync_iteration::DownloadEvent},
-    util::BsonObjects,
 };
-use futures_lite::{Stream, StreamExt, stream};
+use futures_lite::{Stream, StreamExt, future, stream};
+use log::debug;
 use serde::Deserialize;
 use serde_with::{DisplayFromStr, serde_as};
 
+/// A header containing a random id for each request, which helps correlating client and service
+/// logs.
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The maximum amount of bytes read from responses with an unexpected status code to include in
+/// errors.
+const MAX_ERROR_BODY_LENGTH: usize = 4096;
+
+/// How long to wait for the body of responses with an unexpected status code before reporting
+/// the error with the part of the body received so far.
+const ERROR_BODY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const ACCEPT_PREFER_BSON: &str =
+    "application/vnd.powersync.bson-stream;q=0.9,application/x-ndjson;q=0.8";
+const ACCEPT_PREFER_NDJSON: &str =
+    "application/x-ndjson;q=0.9,application/vnd.powersync.bson-stream;q=0.8";
+
 /// Requests a stream of [DownloadEvent]s (more specifically text or binary lines) by opening a
 /// connection to the PowerSync service.
 pub fn sync_stream(
     db: Arc<InnerPowerSyncState>,
     auth: PowerSyncCredentials,
     request_body: String,
+    options: &SyncOptions,
 ) -> impl Stream<Item = Result<DownloadEvent, PowerSyncError>> {
+    let prefer_ndjson = options.prefer_ndjson;
+    let user_agent = options.user_agent.clone();
+    let custom_headers = options.headers.clone();
+    let options = options.clone();
+
     let response = async move {
+        let request_id = db.generate_request_id();
+        debug!("Opening sync stream, request id {request_id}");
+
         let request = Request {
             method: "POST",
             url: auth.parsed_endpoint("sync/stream")?,
             headers: {
                 let mut headers: Vec<(&str, Cow<'_, str>)> = vec![];
+                headers.push((REQUEST_ID_HEADER, request_id.into()));
+                headers.push(("User-Agent", user_agent.into()));
                 headers.push(("Content-Type", "application/json".into()));
                 headers.push(("Authorization", format!("Token {}", auth.token).into()));
                 headers.push((
                     "Accept",
-                    "application/vnd.powersync.bson-stream;q=0.9,application/x-ndjson;q=0.8".into(),
+                    if prefer_ndjson {
+                        ACCEPT_PREFER_NDJSON
+                    } else {
+                        ACCEPT_PREFER_BSON
+                    }
+                    .into(),
                 ));
+                headers.extend(custom_headers);
 
                 headers
             },
@@ -39,10 +75,11 @@ pub fn sync_stream(
         };
 
         let response = db.env.client.send(request).await?;
-        check_ok(response.status)?;
-
-        Ok::<Response, PowerSyncError>(response)
+        check_ok(&db, response, &options).await
     };
+    #[cfg(feature = "tracing")]
+    let response =
+        tracing::Instrument::instrument(response, tracing::info_span!("establish_sync_stream"));
 
     let stream = stream::once_future(response);
 
@@ -53,23 +90,55 @@ pub fn sync_stream(
     })
 }
 
+/// Opens a sync stream without applying any data, returning once the first sync line has been
+/// received from the service.
+///
+/// The `request` body is obtained from the core extension by the download actor, so it matches
+/// the one sent when connecting. The stream is closed afterwards. Errors (e.g. rejected
+/// credentials) are returned directly instead of being retried.
+pub async fn test_sync_stream(
+    db: Arc<InnerPowerSyncState>,
+    auth: PowerSyncCredentials,
+    request: String,
+    options: &SyncOptions,
+) -> Result<(), PowerSyncError> {
+    let mut stream = pin!(sync_stream(db, auth, request, options));
+    while let Some(event) = stream.next().await {
+        match event? {
+            DownloadEvent::TextLine { .. } | DownloadEvent::BinaryLine { .. } => return Ok(()),
+            _ => continue,
+        }
+    }
+
+    Err(RawPowerSyncError::SyncServiceResponseParsing {
+        desc: "Stream closed before receiving a sync line",
+    }
+    .into())
+}
+
 /// Requests a write checkpoint from the sync service.
 pub async fn write_checkpoint(
     db: &InnerPowerSyncState,
     client_id: &str,
     auth: PowerSyncCredentials,
+    options: &SyncOptions,
 ) -> Result<i64, PowerSyncError> {
     let mut url = auth.parsed_endpoint("write-checkpoint2.json")?;
     url.set_query(Some(&format!("client_id={}", client_id)));
+    let request_id = db.generate_request_id();
+    debug!("Requesting write checkpoint, request id {request_id}");
 
     let request = Request {
         method: "GET",
         url,
         headers: {
             let mut headers: Vec<(&str, Cow<'_, str>)> = vec![];
+            headers.push((REQUEST_ID_HEADER, request_id.into()));
+            headers.push(("User-Agent", options.user_agent.clone().into()));
             headers.push(("Content-Type", "application/json".into()));
             headers.push(("Authorization", format!("Token {}", auth.token).into()));
             headers.push(("Accept", "application/json".into()));
+            headers.extend(options.headers.iter().cloned());
 
             headers
         },
@@ -77,7 +146,7 @@ pub async fn write_checkpoint(
     };
 
     let response = db.env.client.send(request).await?;
-    check_ok(response.status)?;
+    let response = check_ok(db, response, options).await?;
 
     #[derive(Deserialize)]
     struct WriteCheckpointResponse {
@@ -97,18 +166,43 @@ pub async fn write_checkpoint(
     Ok(response.data.write_checkpoint)
 }
 
-fn check_ok(code: u16) -> Result<(), PowerSyncError> {
-    match code {
-        200 => Ok(()),
-        401 => Err(RawPowerSyncError::InvalidCredentials.into()),
-        _ => Err(RawPowerSyncError::UnexpectedStatusCode { code }.into()),
+/// Returns the response if it has a successful status code, or an error containing the start of
+/// the response body otherwise.
+///
+/// When the service rejects credentials, they're removed from the cache in `options` so that the
+/// next request fetches new credentials from the connector. Reading the body is bounded by
+/// [MAX_ERROR_BODY_LENGTH] and [ERROR_BODY_TIMEOUT], so a stalled response can't delay the error.
+async fn check_ok(
+    db: &InnerPowerSyncState,
+    response: Response,
+    options: &SyncOptions,
+) -> Result<Response, PowerSyncError> {
+    match response.status {
+        200 => Ok(response),
+        401 => {
+            options.invalidate_credentials();
+            Err(RawPowerSyncError::InvalidCredentials.into())
+        }
+        code => {
+            let mut body = Vec::new();
+            let read = async {
+                // Errors reading the body are ignored, we report the status code either way.
+                let _ = response
+                    .body
+                    .read_up_to(MAX_ERROR_BODY_LENGTH, &mut body)
+                    .await;
+            };
+            future::or(read, db.env.timer.delay_once(ERROR_BODY_TIMEOUT)).await;
+
+            body.truncate(MAX_ERROR_BODY_LENGTH);
+            let body = (!body.is_empty()).then(|| String::from_utf8_lossy(&body).into_owned());
+
+            Err(RawPowerSyncError::UnexpectedStatusCode { code, body }.into())
+        }
     }
 }
 
-/// Reads sync lines from an HTTP response stream.
-///
-/// For JSON responses, this splits at newline chars. For BSON responses, this tracks the length
-/// prefix to split at objects.
+/// Reads sync lines from an HTTP response stream, see [parse_sync_lines].
 fn response_to_lines(
     response: Result<Response, PowerSyncError>,
 ) -> impl Stream<Item = Result<DownloadEvent, PowerSyncError>> {
@@ -117,24 +211,7 @@ fn response_to_lines(
         Err(e) => return stream::once(Err::<DownloadEvent, PowerSyncError>(e)).boxed(),
     };
 
-    let is_bson = match &response.content_type {
-        None => false,
-        Some(value) => value.contains("vnd.powersync.bson-stream"),
-    };
-
-    if is_bson {
-        BsonObjects::new(response.body.reader)
-            .map(|event| match event {
-                Ok(line) => Ok(DownloadEvent::BinaryLine { data: line }),
-                Err(e) => Err(e),
-            })
-            .boxed()
-    } else {
-        LineSplitter::from(response.body.reader)
-            .map(|event| match event {
-                Ok(line) => Ok(DownloadEvent::TextLine { data: line }),
-                Err(e) => Err(e),
-            })
-            .boxed()
-    }
+    parse_sync_lines(response.body.reader, response.content_type.as_deref())
+        .map(|line| line.map(DownloadEvent::from))
+        .boxed()
 }
@@ -3,3 +3,4 @@ pub mod http;
 mod sync_iteration;
 
 pub use actor::{DownloadActor, DownloadActorCommand};
+pub(crate) use sync_iteration::DownloadEvent;
@@ -1,17 +1,22 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use futures_lite::{StreamExt, future, stream::Boxed as BoxedStream};
+use futures_lite::{
+    StreamExt,
+    future::{self, Boxed},
+    stream::Boxed as BoxedStream,
+};
 use log::{debug, info, trace, warn};
 use powersync_sqlite_nostd::{Destructor, ManagedStmt, ResultCode};
 use serde::Serialize;
 use serde_json::value::RawValue;
 
 use crate::db::connection::{SqliteConnection, TransactionGuard};
+use crate::http::SyncLine;
 use crate::schema::SchemaOrCustom;
 use crate::{
     SyncOptions,
     db::internal::InnerPowerSyncState,
-    error::PowerSyncError,
+    error::{PowerSyncError, RawPowerSyncError},
     sync::{
         download::http::sync_stream,
         instruction::{CloseSyncStream, Instruction, LogSeverity},
@@ -22,6 +27,9 @@ use crate::{
 pub struct DownloadClient {
     db: Arc<InnerPowerSyncState>,
     stream: Option<BoxedStream<Result<DownloadEvent, PowerSyncError>>>,
+    /// A timer expiring if the sync service doesn't send a line in time, configured through
+    /// [SyncOptions::with_connect_timeout] and [SyncOptions::with_idle_timeout].
+    line_timeout: Option<LineTimeout>,
     receive_commands: async_channel::Receiver<DownloadEvent>,
 }
 
@@ -33,6 +41,7 @@ impl DownloadClient {
         Self {
             db,
             stream: None,
+            line_timeout: None,
             receive_commands: events,
         }
     }
@@ -43,7 +52,7 @@ impl DownloadClient {
                 Some(stream) => {
                     future::or(
                         Self::receive_command(&self.receive_commands),
-                        Self::receive_on_stream(stream),
+                        Self::receive_on_stream(stream, &mut self.line_timeout),
                     )
                     .await
                 }
@@ -51,7 +60,18 @@ impl DownloadClient {
             }?;
 
             trace!("Handling event {event:?}");
+            if let DownloadEvent::TextLine { .. } | DownloadEvent::BinaryLine { .. } = event {
+                self.line_timeout = LineTimeout::new(&self.db, options.idle_timeout, false);
+            }
+            if let Some(observer) = options.observer() {
+                match &event {
+                    DownloadEvent::TextLine { data } => observer.on_line_received(data.len()),
+                    DownloadEvent::BinaryLine { data } => observer.on_line_received(data.len()),
+                    _ => {}
+                }
+            }
             let mut conn = self.db.writer().await?;
+            let mut prefetch_credentials = false;
 
             for instr in event.invoke_control(conn.sqlite_connection_mut())? {
                 trace!("Handling instruction {instr:?}");
@@ -62,11 +82,15 @@ impl DownloadClient {
                         LogSeverity::Info => info!("{}", line),
                         LogSeverity::Warning => warn!("{}", line),
                     },
-                    Instruction::UpdateSyncStatus { status } => {
-                        self.db.status.update(|s| s.update_from_core(status))
-                    }
+                    Instruction::UpdateSyncStatus { status } => self
+                        .db
+                        .status
+                        .update(|s| s.update_from_core(status, self.db.env.clock)),
                     Instruction::EstablishSyncStream { request } => {
-                        trace!("Establishing sync stream with {request}");
+                        debug!("Establishing sync stream with {request}");
+                        if let Some(observer) = options.observer() {
+                            observer.on_sync_request(request.get());
+                        }
                         Self::establish_sync_stream(
                             Arc::clone(&self.db),
                             &mut self.stream,
@@ -74,16 +98,23 @@ impl DownloadClient {
                             &options,
                         )
                         .await?;
+                        self.line_timeout =
+                            LineTimeout::new(&self.db, options.connect_timeout, true);
 
                         // Trigger a crud upload after establishing a sync stream.
                         if let Some(sync) = self.db.sync.upgrade() {
                             sync.trigger_crud_uploads().await;
                         }
                     }
-                    Instruction::FetchCredentials { .. } => {
-                        // TODO: Pre-fetching credentials
-                        // If did_expire is true, the core extension will also emit a stop
-                        // instruction. So we don't have to handle that separately.
+                    Instruction::FetchCredentials { did_expire } => {
+                        if did_expire {
+                            // The core extension will also emit a stop instruction, so we only
+                            // have to make sure the next connection attempt doesn't reuse cached
+                            // credentials.
+                            options.invalidate_credentials();
+                        } else {
+                            prefetch_credentials = true;
+                        }
                     }
                     Instruction::CloseSyncStream(close) => {
                         break 'event Ok(close);
@@ -91,10 +122,23 @@ impl DownloadClient {
                     Instruction::FlushFileSystem {} => {
                         // Not applicable outside of Dart web.
                     }
-                    Instruction::DidCompleteSync {} => self
-                        .db
-                        .status
-                        .update(|status| status.clear_download_errors()),
+                    Instruction::DidCompleteSync {} => {
+                        self.db.status.update(|status| {
+                            status.clear_download_errors();
+                            status.set_did_complete_sync(true);
+                        });
+                        if let Some(observer) = options.observer() {
+                            observer.on_checkpoint_complete();
+                        }
+                    }
+                }
+            }
+
+            if prefetch_credentials {
+                // Don't block other writers while talking to the connector.
+                drop(conn);
+                if let Err(e) = options.prefetch_credentials().await {
+                    warn!("Could not pre-fetch credentials: {e}");
                 }
             }
         }
@@ -106,10 +150,12 @@ impl DownloadClient {
         request: Box<RawValue>,
         options: &SyncOptions,
     ) -> Result<(), PowerSyncError> {
-        let credentials = options.connector.fetch_credentials().await?;
+        let credentials = options.credentials(db.env.clock).await?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("endpoint", credentials.endpoint.as_str());
         let request = request.get().to_string();
 
-        *stream = Some(sync_stream(db, credentials, request).boxed());
+        *stream = Some(sync_stream(db, credentials, request, options).boxed());
         Ok(())
     }
 
@@ -121,11 +167,50 @@ impl DownloadClient {
 
     async fn receive_on_stream(
         stream: &mut BoxedStream<Result<DownloadEvent, PowerSyncError>>,
+        line_timeout: &mut Option<LineTimeout>,
     ) -> Result<DownloadEvent, PowerSyncError> {
-        Ok(stream
-            .try_next()
-            .await?
-            .unwrap_or(DownloadEvent::ResponseStreamEnd))
+        let next_event = async {
+            Ok::<_, PowerSyncError>(
+                stream
+                    .try_next()
+                    .await?
+                    .unwrap_or(DownloadEvent::ResponseStreamEnd),
+            )
+        };
+
+        match line_timeout {
+            Some(timeout) => {
+                let expired = async {
+                    (&mut timeout.timer).await;
+                    Err(if timeout.connecting {
+                        RawPowerSyncError::ConnectTimeout
+                    } else {
+                        RawPowerSyncError::IdleTimeout
+                    }
+                    .into())
+                };
+
+                future::or(next_event, expired).await
+            }
+            None => next_event.await,
+        }
+    }
+}
+
+/// A timer racing against the next line received from the sync service.
+struct LineTimeout {
+    timer: Boxed<()>,
+    /// Whether this timeout is waiting for the first line after opening a connection, as opposed
+    /// to waiting for subsequent lines on an established stream.
+    connecting: bool,
+}
+
+impl LineTimeout {
+    fn new(db: &InnerPowerSyncState, timeout: Option<Duration>, connecting: bool) -> Option<Self> {
+        timeout.map(|timeout| Self {
+            timer: db.env.timer.delay_once(timeout),
+            connecting,
+        })
     }
 }
 
@@ -154,6 +239,15 @@ pub enum DownloadEvent {
     UpdateSubscriptions { keys: Vec<StreamKey> },
 }
 
+impl From<SyncLine> for DownloadEvent {
+    fn from(line: SyncLine) -> Self {
+        match line {
+            SyncLine::Text(data) => DownloadEvent::TextLine { data },
+            SyncLine::Binary(data) => DownloadEvent::BinaryLine { data },
+        }
+    }
+}
+
 impl DownloadEvent {
     fn into_powersync_control_argument(self) -> (&'static str, PowerSyncControlArgument) {
         use PowerSyncControlArgument::*;
@@ -240,3 +334,46 @@ pub struct StartDownloadIteration {
     pub include_defaults: bool,
     pub active_streams: Vec<StreamKey>,
 }
+
+impl StartDownloadIteration {
+    pub fn new(db: &InnerPowerSyncState, options: &SyncOptions) -> Self {
+        Self {
+            parameters: serde_json::Value::Object(options.parameters.clone()),
+            schema: db.schema.clone(),
+            include_defaults: options.include_default_streams,
+            active_streams: db.current_streams.collect_active_streams(),
+        }
+    }
+}
+
+/// Asks the core extension for the request body it would use to open a sync stream with the given
+/// `options`, without starting a sync iteration.
+///
+/// This starts and immediately stops the sync client of the core extension, so it must not be
+/// called while a sync iteration is active. The download actor ensures that by only resolving
+/// requests while it's idle.
+pub async fn resolve_sync_request(
+    db: &InnerPowerSyncState,
+    options: &SyncOptions,
+) -> Result<String, PowerSyncError> {
+    let start = StartDownloadIteration::new(db, options);
+    let mut writer = db.writer().await?;
+    let conn = writer.sqlite_connection_mut();
+
+    let instructions = DownloadEvent::Start(start).invoke_control(conn)?;
+    // We only need the request, so reset the sync client in the core extension.
+    DownloadEvent::Stop.invoke_control(conn)?;
+
+    instructions
+        .into_iter()
+        .find_map(|instr| match instr {
+            Instruction::EstablishSyncStream { request } => Some(request.get().to_string()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            RawPowerSyncError::SyncServiceResponseParsing {
+                desc: "The core extension did not request a sync stream",
+            }
+            .into()
+        })
+}
@@ -1,9 +1,10 @@
+use std::collections::BTreeMap;
 use std::time::{Duration, SystemTime};
 
-use serde::{Deserialize, Serialize, de::IgnoredAny};
+use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 
-use crate::{sync::progress::ProgressCounters, util::SerializedJsonObject};
+use crate::{StreamPriority, sync::progress::ProgressCounters, util::SerializedJsonObject};
 
 /// An instruction sent by the core extension to the SDK.
 #[derive(Deserialize, Debug)]
@@ -25,8 +26,7 @@ pub enum Instruction {
         /// Whether the credentials currently used have expired.
         ///
         /// If false, this is a pre-fetch.
-        #[serde(rename = "did_expire")]
-        _did_expire: bool,
+        did_expire: bool,
     },
     // These are defined like this because deserializers in Kotlin can't support either an
     // object or a literal value
@@ -66,10 +66,68 @@ pub struct DownloadSyncStatus {
     /// Whether we've requested the client SDK to connect to the socket while not receiving sync
     /// lines yet.
     pub connecting: bool,
+    /// Sync streams tracked by the sync client, including their download progress.
     pub streams: Vec<ActiveStreamSubscription>,
-    pub downloading: Option<IgnoredAny>,
+    /// Progress of the checkpoint currently being downloaded, if any.
+    pub downloading: Option<DownloadProgress>,
+    /// The last completed sync for each priority, with [StreamPriority::SENTINEL] representing
+    /// a complete sync across all priorities.
+    #[serde(default)]
+    pub priority_status: Vec<SyncPriorityStatus>,
 }
 
+/// Progress towards the checkpoint currently being downloaded, tracked per bucket.
+#[derive(Deserialize, Debug, Default)]
+pub struct DownloadProgress {
+    pub buckets: BTreeMap<String, BucketProgress>,
+}
+
+impl DownloadProgress {
+    /// Download progress across all buckets.
+    pub fn overall(&self) -> ProgressCounters {
+        self.for_priority(StreamPriority::SENTINEL)
+    }
+
+    /// Download progress of buckets with the given priority or a higher one.
+    pub fn for_priority(&self, priority: StreamPriority) -> ProgressCounters {
+        let mut counters = ProgressCounters {
+            total: 0,
+            downloaded: 0,
+        };
+
+        for bucket in self.buckets.values() {
+            if bucket.priority.priority_number() <= priority.priority_number() {
+                counters.total += bucket.target_count - bucket.at_last;
+                counters.downloaded += bucket.since_last;
+            }
+        }
+
+        counters
+    }
+}
+
+/// Download progress for a single bucket, as reported by the core extension.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BucketProgress {
+    pub priority: StreamPriority,
+    /// The amount of operations in this bucket at the last completed checkpoint.
+    pub at_last: i64,
+    /// The amount of operations downloaded since the last completed checkpoint.
+    pub since_last: i64,
+    /// The amount of operations in this bucket at the checkpoint being downloaded.
+    pub target_count: i64,
+}
+
+/// Information about the last completed sync of streams with a given priority.
+#[derive(Deserialize, Debug)]
+pub struct SyncPriorityStatus {
+    pub priority: StreamPriority,
+    /// When streams with this priority (or a higher one) have last been synced.
+    pub last_synced_at: Option<Timestamp>,
+    pub has_synced: Option<bool>,
+}
+
+/// A sync stream tracked by the sync client, as reported in [DownloadSyncStatus::streams].
 #[derive(Deserialize, Serialize, Debug)]
 pub struct ActiveStreamSubscription {
     pub name: String,
@@ -81,15 +139,95 @@ pub struct ActiveStreamSubscription {
     pub expires_at: Option<Timestamp>,
     pub last_synced_at: Option<Timestamp>,
     pub progress: ProgressCounters,
+    #[serde(default)]
+    pub errors: Vec<StreamError>,
 }
 
+/// An error reported by the PowerSync service for a stream subscription, for instance because the
+/// subscription used invalid parameters.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StreamError {
+    pub message: String,
+}
+
+/// A point in time reported by the core extension, in seconds since the Unix epoch.
 #[repr(transparent)]
 #[derive(Deserialize, Serialize, Clone, Copy, Debug)]
 pub struct Timestamp(pub i64);
 
+impl Timestamp {
+    /// Converts this timestamp into a [SystemTime].
+    ///
+    /// Negative values are interpreted as times before the Unix epoch instead of wrapping around.
+    /// Since timestamps are provided by the sync service, values that can't be represented as a
+    /// [SystemTime] on this platform are clamped to the closest representable second instead of
+    /// panicking.
+    pub fn to_system_time(self) -> SystemTime {
+        let from_epoch = |seconds: u64| {
+            let offset = Duration::from_secs(seconds);
+            if self.0 >= 0 {
+                SystemTime::UNIX_EPOCH.checked_add(offset)
+            } else {
+                SystemTime::UNIX_EPOCH.checked_sub(offset)
+            }
+        };
+
+        let seconds = self.0.unsigned_abs();
+        if let Some(time) = from_epoch(seconds) {
+            return time;
+        }
+
+        // Find the largest offset that is still representable. The epoch itself always is.
+        let (mut valid, mut invalid) = (0, seconds);
+        while invalid - valid > 1 {
+            let mid = valid + (invalid - valid) / 2;
+            if from_epoch(mid).is_some() {
+                valid = mid;
+            } else {
+                invalid = mid;
+            }
+        }
+
+        from_epoch(valid).unwrap()
+    }
+}
+
 impl From<Timestamp> for SystemTime {
     fn from(val: Timestamp) -> Self {
-        let since_epoch = Duration::from_secs(val.0 as u64);
-        SystemTime::UNIX_EPOCH + since_epoch
+        val.to_system_time()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime};
+
+    use super::Timestamp;
+
+    #[test]
+    fn timestamp_to_system_time() {
+        // 2023-11-14T22:13:20Z
+        assert_eq!(
+            Timestamp(1_700_000_000).to_system_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        assert_eq!(
+            Timestamp(-60).to_system_time(),
+            SystemTime::UNIX_EPOCH - Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn timestamp_out_of_range() {
+        for timestamp in [i64::MIN, i64::MAX] {
+            let time = Timestamp(timestamp).to_system_time();
+            // The result is clamped to the last representable second.
+            let next = if timestamp < 0 {
+                time.checked_sub(Duration::from_secs(1))
+            } else {
+                time.checked_add(Duration::from_secs(1))
+            };
+            assert!(next.is_none());
+        }
     }
 }
@@ -1,7 +1,8 @@
 pub mod connector;
 pub mod coordinator;
 pub mod download;
-mod instruction;
+pub mod instruction;
+pub mod observer;
 pub mod options;
 pub mod progress;
 pub mod status;
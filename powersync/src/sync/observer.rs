@@ -0,0 +1,43 @@
+use crate::error::PowerSyncError;
+
+/// Receives events about the sync client, e.g. to collect metrics.
+///
+/// All methods have an empty default implementation, so implementations only need to override
+/// the callbacks they're interested in. Callbacks are invoked on the download and upload actors,
+/// so they should return quickly.
+///
+/// Observers are installed with [crate::SyncOptions::with_observer].
+pub trait SyncObserver: Send + Sync {
+    /// Called when the sync client starts a new iteration, either after calling
+    /// [crate::PowerSyncDatabase::connect] or when reconnecting after an error.
+    fn on_iteration_start(&self) {}
+
+    /// Called before opening a sync stream, with `request` being the JSON body sent to the
+    /// PowerSync service.
+    ///
+    /// This is useful to debug sync rules, as the request includes the buckets and streams the
+    /// client subscribes to. Credentials are sent as headers and not part of the request body.
+    fn on_sync_request(&self, request: &str) {
+        let _ = request;
+    }
+
+    /// Called for each sync line received from the PowerSync service, with `bytes` being the size
+    /// of that line.
+    fn on_line_received(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// Called after a complete checkpoint has been applied to the local database.
+    fn on_checkpoint_complete(&self) {}
+
+    /// Called after local writes have been uploaded, with `batches` being the amount of times
+    /// [crate::BackendConnector::upload_data] has been invoked.
+    fn on_upload_complete(&self, batches: usize) {
+        let _ = batches;
+    }
+
+    /// Called when a sync iteration or CRUD upload fails.
+    fn on_error(&self, error: &PowerSyncError) {
+        let _ = error;
+    }
+}
@@ -1,6 +1,17 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::sync::connector::BackendConnector;
+use serde_json::{Map, Value};
+
+use crate::{
+    PowerSyncCredentials,
+    env::{Clock, Random},
+    error::{PowerSyncError, RawPowerSyncError},
+    sync::{connector::BackendConnector, observer::SyncObserver},
+};
 
 /// Options controlling how PowerSync connects to a sync service.
 #[derive(Clone)]
@@ -9,17 +20,149 @@ pub struct SyncOptions {
     pub(crate) connector: Arc<dyn BackendConnector>,
     /// Whether to sync `auto_subscribe: true` streams automatically.
     pub(crate) include_default_streams: bool,
-    /// The retry delay between sync iterations on errors.
-    pub(crate) retry_delay: Duration,
+    /// How to retry sync iterations after errors.
+    pub(crate) retry_policy: RetryPolicy,
+    /// Whether to request newline-delimited JSON instead of BSON sync lines.
+    pub(crate) prefer_ndjson: bool,
+    /// How long to wait for the first sync line after opening a connection.
+    pub(crate) connect_timeout: Option<Duration>,
+    /// How long to wait for subsequent sync lines before considering the stream dead.
+    pub(crate) idle_timeout: Option<Duration>,
+    /// An optional observer notified about sync events.
+    pub(crate) observer: Option<Arc<dyn SyncObserver>>,
+    /// Client parameters included in sync requests, which sync rules can reference.
+    pub(crate) parameters: Map<String, Value>,
+    /// The `User-Agent` header sent with requests to the PowerSync service.
+    pub(crate) user_agent: String,
+    /// Additional headers sent with requests to the PowerSync service.
+    pub(crate) headers: Vec<(&'static str, Cow<'static, str>)>,
+    /// Credentials that haven't expired yet, shared between the upload and download actors.
+    credentials: Arc<Mutex<Option<CachedCredentials>>>,
+}
+
+/// Controls how the sync client retries after a failed sync iteration.
+///
+/// The delay starts at [Self::initial_delay] and doubles after each consecutive failure, up to
+/// [Self::max_delay]. With [Self::jitter], the actual delay is chosen randomly between zero and
+/// that value. The default policy retries indefinitely with a jittered delay of up to 5 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The delay after the first failed attempt.
+    pub initial_delay: Duration,
+    /// The maximum delay between attempts.
+    pub max_delay: Duration,
+    /// After this many consecutive failed sync iterations, the sync client stops retrying and
+    /// reports the last error as [crate::SyncStatusData::fatal_error].
+    ///
+    /// When [None] (the default), the client retries indefinitely.
+    pub max_attempts: Option<usize>,
+    /// Whether to pick a random delay between zero and the computed backoff ("full jitter").
+    ///
+    /// This avoids many clients reconnecting at the same time after a service restart. It's
+    /// enabled by default, the random source can be configured with
+    /// [crate::env::PowerSyncEnvironment::with_random].
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Retries with delays starting at `initial_delay` and doubling until `max_delay` is reached.
+    pub fn exponential(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            max_attempts: None,
+            jitter: true,
+        }
+    }
+
+    /// Stops retrying after `attempts` consecutive failures.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Enables or disables [Self::jitter].
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The delay to wait for after `attempt` consecutive failures.
+    pub(crate) fn delay(&self, attempt: usize, random: &dyn Random) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(u32::BITS as usize - 1) as u32;
+        let backoff = self
+            .initial_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        if self.jitter {
+            random_between(random, Duration::ZERO, backoff)
+        } else {
+            backoff
+        }
+    }
+
+    /// Whether no further attempts should be made after `failed_attempts` consecutive failures.
+    pub(crate) fn is_exhausted(&self, failed_attempts: usize) -> bool {
+        self.max_attempts
+            .is_some_and(|max_attempts| failed_attempts >= max_attempts)
+    }
+}
+
+/// Returns a random duration in the inclusive range between `min` and `max`.
+fn random_between(random: &dyn Random, min: Duration, max: Duration) -> Duration {
+    let range = u64::try_from((max - min).as_nanos()).unwrap_or(u64::MAX);
+    let offset = match range.checked_add(1) {
+        Some(bound) => random.next_u64() % bound,
+        None => random.next_u64(),
+    };
+
+    min + Duration::from_nanos(offset)
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        let delay = Duration::from_secs(5);
+        Self::exponential(delay, delay)
+    }
+}
+
+struct CachedCredentials {
+    credentials: PowerSyncCredentials,
+    /// Whether these credentials have been pre-fetched before the current ones expire.
+    ///
+    /// Pre-fetched credentials are used for the next request even if they don't have an expiry
+    /// date.
+    prefetched: bool,
+}
+
+impl CachedCredentials {
+    fn is_valid(&self, clock: &dyn Clock) -> bool {
+        let now = clock.now();
+        self.credentials
+            .expires_at
+            .is_some_and(|expires_at| expires_at > now)
+    }
 }
 
 impl SyncOptions {
+    const DEFAULT_USER_AGENT: &'static str =
+        concat!("powersync-native/", env!("CARGO_PKG_VERSION"));
+
     /// Creates new [SyncOptions] with default options given the [BackendConnector].
     pub fn new(connector: impl BackendConnector + 'static) -> Self {
         Self {
             connector: Arc::new(connector),
             include_default_streams: true,
-            retry_delay: Duration::from_secs(5),
+            retry_policy: RetryPolicy::default(),
+            prefer_ndjson: false,
+            connect_timeout: None,
+            idle_timeout: None,
+            observer: None,
+            parameters: Map::new(),
+            user_agent: Self::DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+            credentials: Default::default(),
         }
     }
 
@@ -30,8 +173,403 @@ impl SyncOptions {
         self.include_default_streams = include;
     }
 
+    /// Whether streams with `auto_subscribe: true` are synced, see
+    /// [Self::set_include_default_streams].
+    pub fn include_default_streams(&self) -> bool {
+        self.include_default_streams
+    }
+
     /// Configures the delay after a failed sync iteration (the default is 5 seconds).
+    ///
+    /// This doesn't use exponential backoff, see [Self::with_retry_policy]. Unless
+    /// [RetryPolicy::jitter] is disabled, a random delay of up to `delay` is used.
     pub fn with_retry_delay(&mut self, delay: Duration) {
-        self.retry_delay = delay;
+        self.retry_policy.initial_delay = delay;
+        self.retry_policy.max_delay = delay;
+    }
+
+    /// Configures how failed sync iterations and CRUD uploads are retried, see [RetryPolicy].
+    pub fn with_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Configures how long to wait for the sync service to send the first line after opening a
+    /// connection.
+    ///
+    /// If the timeout expires, the sync iteration fails and is retried after the retry delay. By
+    /// default, there is no timeout.
+    pub fn with_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = Some(timeout);
+    }
+
+    /// Configures how long an established sync stream may stay silent before the connection is
+    /// considered dead.
+    ///
+    /// The timeout is reset on every received line. When it expires, the sync iteration fails and
+    /// is retried after the retry delay. This detects half-open connections that TCP keepalives
+    /// may miss. By default, there is no idle timeout.
+    pub fn with_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = Some(timeout);
+    }
+
+    /// Whether to request sync lines as newline-delimited JSON instead of BSON.
+    ///
+    /// By default, the SDK prefers BSON responses. Some proxies don't forward the vendor-specific
+    /// BSON content type though, which can be worked around by enabling this option.
+    pub fn prefer_ndjson(&mut self, prefer: bool) {
+        self.prefer_ndjson = prefer;
+    }
+
+    /// Installs a [SyncObserver] receiving events about sync iterations and uploads.
+    pub fn with_observer(&mut self, observer: Arc<dyn SyncObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Configures client parameters sent to the sync service when connecting.
+    ///
+    /// Sync rules can reference these parameters, e.g. to only sync data for a given tenant.
+    /// Client parameters must be a JSON object, but values in that object can be arbitrary JSON
+    /// values, including nested arrays and objects.
+    pub fn with_parameters(&mut self, parameters: Map<String, Value>) {
+        self.parameters = parameters;
+    }
+
+    /// Whether `other` uses the same [BackendConnector] instance as these options.
+    pub(crate) fn has_same_connector(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.connector, &other.connector)
+    }
+
+    /// Whether connecting with `other` would result in the same sync requests as connecting with
+    /// these options.
+    ///
+    /// Connectors and observers are compared by identity, so options are only equivalent if they
+    /// are clones of each other or use the same shared instances.
+    pub(crate) fn is_equivalent(&self, other: &Self) -> bool {
+        let same_observer = match (&self.observer, &other.observer) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.has_same_connector(other)
+            && same_observer
+            && self.include_default_streams == other.include_default_streams
+            && self.retry_policy == other.retry_policy
+            && self.prefer_ndjson == other.prefer_ndjson
+            && self.connect_timeout == other.connect_timeout
+            && self.idle_timeout == other.idle_timeout
+            && self.parameters == other.parameters
+            && self.user_agent == other.user_agent
+            && self.headers == other.headers
+    }
+
+    /// Configures the `User-Agent` header sent with requests to the PowerSync service.
+    ///
+    /// By default, this is `powersync-native/<version>`.
+    pub fn with_user_agent(&mut self, user_agent: String) {
+        self.user_agent = user_agent;
+    }
+
+    /// Adds a header sent with all requests to the PowerSync service, e.g. for gateways requiring
+    /// API keys.
+    ///
+    /// Custom headers are added after the headers set by the SDK. Adding a header with the same
+    /// name again replaces the previous value. The `Authorization` header can't be overridden, as
+    /// it's derived from [BackendConnector::fetch_credentials].
+    pub fn with_header(
+        &mut self,
+        name: &'static str,
+        value: impl Into<Cow<'static, str>>,
+    ) -> Result<(), PowerSyncError> {
+        if name.eq_ignore_ascii_case("Authorization") {
+            return Err(PowerSyncError::argument_error(
+                "The Authorization header is derived from credentials and can't be overridden",
+            ));
+        }
+
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self.headers.push((name, value.into()));
+        Ok(())
+    }
+
+    pub(crate) fn observer(&self) -> Option<&dyn SyncObserver> {
+        self.observer.as_deref()
+    }
+
+    /// Returns cached credentials if they haven't expired yet, or fetches new credentials from
+    /// the connector otherwise.
+    ///
+    /// The `clock` is used to determine whether cached credentials have expired.
+    pub(crate) async fn credentials(
+        &self,
+        clock: &dyn Clock,
+    ) -> Result<PowerSyncCredentials, PowerSyncError> {
+        {
+            let mut cached = self.credentials.lock().unwrap();
+            if let Some(entry) = &mut *cached {
+                if entry.prefetched {
+                    entry.prefetched = false;
+                    let credentials = entry.credentials.clone();
+                    if !entry.is_valid(clock) {
+                        *cached = None;
+                    }
+
+                    return Ok(credentials);
+                } else if entry.is_valid(clock) {
+                    return Ok(entry.credentials.clone());
+                }
+            }
+        }
+
+        let credentials = self.fetch_from_connector().await?;
+        self.store_credentials(&credentials, false);
+        Ok(credentials)
+    }
+
+    async fn fetch_from_connector(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+        self.connector
+            .fetch_credentials()
+            .await
+            .map_err(|source| RawPowerSyncError::ConnectorCredentials { source }.into())
+    }
+
+    /// Fetches credentials from the connector ahead of time, so that the next request doesn't
+    /// have to wait for them.
+    pub(crate) async fn prefetch_credentials(&self) -> Result<(), PowerSyncError> {
+        let credentials = self.fetch_from_connector().await?;
+        self.store_credentials(&credentials, true);
+        Ok(())
+    }
+
+    fn store_credentials(&self, credentials: &PowerSyncCredentials, prefetched: bool) {
+        if prefetched || credentials.expires_at.is_some() {
+            *self.credentials.lock().unwrap() = Some(CachedCredentials {
+                credentials: credentials.clone(),
+                prefetched,
+            });
+        }
+    }
+
+    /// Clears cached credentials, e.g. after the sync service reported them as expired or rejected
+    /// them.
+    pub(crate) fn invalidate_credentials(&self) {
+        *self.credentials.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        error::Error,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use async_trait::async_trait;
+    use futures_lite::future;
+
+    use super::{RetryPolicy, SyncOptions};
+    use crate::env::{Clock, PowerSyncEnvironment, SeededRandom};
+    use crate::{
+        BackendConnector, PowerSyncCredentials,
+        error::{PowerSyncError, PowerSyncErrorKind},
+    };
+
+    struct CountingConnector {
+        calls: Arc<AtomicUsize>,
+        expires_in: Option<Duration>,
+    }
+
+    #[async_trait]
+    impl BackendConnector for CountingConnector {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(PowerSyncCredentials {
+                endpoint: "https://powersync.example.com".to_string(),
+                token: "token".to_string(),
+                expires_at: self.expires_in.map(|d| SystemTime::now() + d),
+            })
+        }
+
+        async fn upload_data(&self) -> Result<(), PowerSyncError> {
+            Ok(())
+        }
+    }
+
+    fn fetch_count(expires_in: Option<Duration>, invalidate: bool) -> usize {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let options = SyncOptions::new(CountingConnector {
+            calls: calls.clone(),
+            expires_in,
+        });
+        // Clones share the cache, like the options passed to upload and download actors.
+        let other = options.clone();
+
+        future::block_on(async {
+            options
+                .credentials(PowerSyncEnvironment::system_clock())
+                .await
+                .unwrap();
+            if invalidate {
+                options.invalidate_credentials();
+            }
+            other
+                .credentials(PowerSyncEnvironment::system_clock())
+                .await
+                .unwrap();
+        });
+
+        calls.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn caches_credentials() {
+        assert_eq!(fetch_count(Some(Duration::from_secs(3600)), false), 1);
+    }
+
+    #[test]
+    fn refetches_without_expiry() {
+        assert_eq!(fetch_count(None, false), 2);
+    }
+
+    #[test]
+    fn refetches_expired_credentials() {
+        assert_eq!(fetch_count(Some(Duration::ZERO), false), 2);
+    }
+
+    #[test]
+    fn checks_expiry_with_clock() {
+        struct FutureClock;
+        impl Clock for FutureClock {
+            fn now(&self) -> SystemTime {
+                SystemTime::now() + Duration::from_secs(7200)
+            }
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let options = SyncOptions::new(CountingConnector {
+            calls: calls.clone(),
+            expires_in: Some(Duration::from_secs(3600)),
+        });
+
+        future::block_on(async {
+            options.credentials(&FutureClock).await.unwrap();
+            // According to the clock, the cached credentials have expired an hour ago.
+            options.credentials(&FutureClock).await.unwrap();
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn refetches_after_invalidation() {
+        assert_eq!(fetch_count(Some(Duration::from_secs(3600)), true), 2);
+    }
+
+    #[test]
+    fn uses_prefetched_credentials_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let options = SyncOptions::new(CountingConnector {
+            calls: calls.clone(),
+            expires_in: None,
+        });
+
+        future::block_on(async {
+            options.prefetch_credentials().await.unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            options
+                .credentials(PowerSyncEnvironment::system_clock())
+                .await
+                .unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            // Without an expiry date, credentials are only reused once.
+            options
+                .credentials(PowerSyncEnvironment::system_clock())
+                .await
+                .unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn exponential_retry_delay() {
+        let random = PowerSyncEnvironment::system_random();
+        let policy = RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(10))
+            .with_max_attempts(5)
+            .with_jitter(false);
+
+        let delays: Vec<_> = (1..=6)
+            .map(|attempt| policy.delay(attempt, random).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+        assert_eq!(policy.delay(usize::MAX, random), Duration::from_secs(10));
+
+        assert!(!policy.is_exhausted(4));
+        assert!(policy.is_exhausted(5));
+        assert!(!RetryPolicy::default().is_exhausted(usize::MAX));
+    }
+
+    #[test]
+    fn jittered_retry_delay() {
+        let policy = RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(10));
+        let delays = |seed| {
+            let random = SeededRandom::new(seed);
+            (1..=6)
+                .map(|attempt| policy.delay(attempt, &random))
+                .collect::<Vec<_>>()
+        };
+
+        let jittered = delays(42);
+        for (attempt, delay) in (1..=6).zip(&jittered) {
+            let backoff = policy
+                .with_jitter(false)
+                .delay(attempt, &SeededRandom::new(0));
+            assert!(*delay <= backoff, "{delay:?} exceeds {backoff:?}");
+        }
+
+        // The same seed results in the same delays, different seeds spread clients out.
+        assert_eq!(jittered, delays(42));
+        assert_ne!(jittered, delays(43));
+    }
+
+    #[test]
+    fn rejects_authorization_header() {
+        let mut options = SyncOptions::new(CountingConnector {
+            calls: Default::default(),
+            expires_in: None,
+        });
+
+        options.with_header("x-api-key", "a").unwrap();
+        options.with_header("X-API-Key", "b").unwrap();
+        assert_eq!(options.headers, vec![("X-API-Key", "b".into())]);
+
+        assert!(options.with_header("authorization", "Token foo").is_err());
+    }
+
+    #[test]
+    fn classifies_connector_errors() {
+        struct FailingConnector;
+
+        #[async_trait]
+        impl BackendConnector for FailingConnector {
+            async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+                Err(PowerSyncError::argument_error("not logged in"))
+            }
+
+            async fn upload_data(&self) -> Result<(), PowerSyncError> {
+                Ok(())
+            }
+        }
+
+        let options = SyncOptions::new(FailingConnector);
+        let err = future::block_on(options.credentials(PowerSyncEnvironment::system_clock()))
+            .unwrap_err();
+        assert_eq!(err.kind(), PowerSyncErrorKind::ConnectorCredentials);
+        assert!(err.source().is_some());
     }
 }
@@ -19,10 +19,25 @@ impl ProgressCounters {
     /// When this number reaches `1.0`, all changes have been received form the sync service.
     /// Actually applying these changes happens before the [ProgressCounters] field is cleared
     /// though, so progress can stay at `1.0` for a short while before completing.
+    /// If there is nothing to download, this returns `1.0` as well.
     pub fn fraction(&self) -> f32 {
         match self.total {
-            0 => 0.0,
+            0 => 1.0,
             _ => (self.downloaded as f32) / (self.total as f32),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ProgressCounters;
+
+    #[test]
+    fn fraction() {
+        let progress = |downloaded, total| ProgressCounters { total, downloaded };
+
+        assert_eq!(progress(0, 0).fraction(), 1.0);
+        assert_eq!(progress(5, 10).fraction(), 0.5);
+        assert_eq!(progress(10, 10).fraction(), 1.0);
+    }
+}
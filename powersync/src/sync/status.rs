@@ -1,9 +1,10 @@
 use std::{
     fmt::Debug,
     sync::{
-        Arc, Mutex,
+        Arc, Mutex, Weak,
         atomic::{AtomicBool, Ordering},
     },
+    time::SystemTime,
 };
 
 use event_listener::{Event, EventListener};
@@ -11,9 +12,11 @@ use powersync_sqlite_nostd::ResultCode;
 
 use crate::db::connection::SqliteConnection;
 use crate::{
+    StreamPriority,
+    env::Clock,
     error::PowerSyncError,
     sync::{
-        instruction::{ActiveStreamSubscription, DownloadSyncStatus},
+        instruction::{ActiveStreamSubscription, DownloadSyncStatus, Timestamp},
         progress::ProgressCounters,
         streams::{StreamDescription, StreamSubscriptionDescription},
     },
@@ -22,12 +25,46 @@ use crate::{
 /// An internal struct holding the current sync status, which allows notifying listeners.
 pub struct SyncStatus {
     data: Mutex<Arc<SyncStatusData>>,
+    callbacks: Arc<StatusCallbacks>,
+    /// Held while installing a new revision and invoking callbacks with it, so that callbacks
+    /// observe revisions in the order they have been installed.
+    dispatch: Mutex<()>,
+}
+
+type StatusCallback = dyn Fn(Arc<SyncStatusData>) + Send + Sync;
+
+#[derive(Default)]
+struct StatusCallbacks {
+    active: Mutex<Vec<Arc<StatusCallback>>>,
 }
 
 impl SyncStatus {
     pub(crate) fn new() -> Self {
         Self {
             data: Default::default(),
+            callbacks: Default::default(),
+            dispatch: Default::default(),
+        }
+    }
+
+    /// Registers a `callback` invoked with the new status after every update.
+    ///
+    /// Each call to [Self::update] invokes the callback exactly once, after the new status has been
+    /// installed. Concurrent updates are dispatched one at a time, so the last invocation always
+    /// receives the current status. The callback is unregistered when the returned
+    /// [StatusChangeListener] is dropped.
+    ///
+    /// This is exposed to users through [crate::PowerSyncDatabase::on_status_changed].
+    pub(crate) fn listener(
+        &self,
+        callback: impl Fn(Arc<SyncStatusData>) + Send + Sync + 'static,
+    ) -> StatusChangeListener {
+        let callback: Arc<StatusCallback> = Arc::new(callback);
+        self.callbacks.active.lock().unwrap().push(callback.clone());
+
+        StatusChangeListener {
+            callbacks: Arc::downgrade(&self.callbacks),
+            callback,
         }
     }
 
@@ -37,20 +74,49 @@ impl SyncStatus {
     }
 
     pub(crate) fn update<T>(&self, update: impl FnOnce(&mut SyncStatusData) -> T) -> T {
+        // Acquired before the data lock, since callbacks may lock the data to read the status.
+        let _dispatch = self.dispatch.lock().unwrap();
+
         // Update status.
         let mut data = self.data.lock().unwrap();
         let mut new = data.new_revision();
         let res = update(&mut new);
 
         // Then notify listeners.
-        let old_state = std::mem::replace(&mut *data, Arc::new(new));
+        let new = Arc::new(new);
+        let old_state = std::mem::replace(&mut *data, new.clone());
         old_state.is_invalidated.store(true, Ordering::SeqCst);
         old_state.invalidated.notify(usize::MAX);
+        drop(data);
+
+        // Invoke callbacks without holding locks, so that they can inspect the status or
+        // unregister themselves.
+        let callbacks = self.callbacks.active.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(new.clone());
+        }
 
         res
     }
 }
 
+/// A callback registered with [crate::PowerSyncDatabase::on_status_changed].
+///
+/// Dropping this handle unregisters the callback.
+pub struct StatusChangeListener {
+    callbacks: Weak<StatusCallbacks>,
+    callback: Arc<StatusCallback>,
+}
+
+impl Drop for StatusChangeListener {
+    fn drop(&mut self) {
+        if let Some(callbacks) = self.callbacks.upgrade() {
+            let mut active = callbacks.active.lock().unwrap();
+            active.retain(|callback| !Arc::ptr_eq(callback, &self.callback));
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum UploadStatus {
     #[default]
@@ -59,11 +125,34 @@ pub enum UploadStatus {
     Error(PowerSyncError),
 }
 
+/// A high-level summary of the sync client's connection, see [SyncStatusData::connection_state].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The sync client is not connected and isn't trying to connect.
+    Disconnected,
+    /// The sync client is establishing a connection to the PowerSync service.
+    Connecting,
+    /// The sync client is connected to the PowerSync service.
+    Connected,
+    /// The last sync iteration failed. Unless [SyncStatusData::fatal_error] is set, the client
+    /// will retry after a delay.
+    Error,
+}
+
 #[derive(Default)]
 pub struct SyncStatusData {
     downloading: Arc<DownloadSyncStatus>,
     download_error: Option<PowerSyncError>,
+    /// Set when the sync client has given up after exceeding [crate::RetryPolicy::max_attempts].
+    fatal_error: Option<PowerSyncError>,
     uploads: UploadStatus,
+    /// Whether the `ps_crud` table contains local writes that haven't been uploaded yet.
+    has_pending_uploads: bool,
+    /// Whether a complete sync has finished since the sync client was last asked to connect.
+    did_complete_sync: bool,
+    /// The time at which [Self::downloading] was reported, according to the environment's
+    /// [Clock].
+    resolved_at: Option<SystemTime>,
 
     /// Raised when a new instance is installed in [SyncStatus].
     is_invalidated: AtomicBool,
@@ -76,7 +165,11 @@ impl SyncStatusData {
         Self {
             downloading: self.downloading.clone(),
             download_error: self.download_error.clone(),
+            fatal_error: self.fatal_error.clone(),
             uploads: Default::default(),
+            has_pending_uploads: self.has_pending_uploads,
+            did_complete_sync: self.did_complete_sync,
+            resolved_at: self.resolved_at,
             is_invalidated: Default::default(),
             invalidated: Default::default(),
         }
@@ -94,14 +187,68 @@ impl SyncStatusData {
         self.downloading.downloading.is_some()
     }
 
+    /// Whether a complete sync across all priorities has been completed at least once.
+    pub fn has_synced(&self) -> bool {
+        self.has_synced_priority(StreamPriority::SENTINEL)
+    }
+
+    /// Whether all streams with the given priority (or a higher one) have been synced at least
+    /// once.
+    pub fn has_synced_priority(&self, priority: StreamPriority) -> bool {
+        self.downloading.priority_status.iter().any(|status| {
+            status.has_synced == Some(true)
+                && status.priority.priority_number() >= priority.priority_number()
+        })
+    }
+
+    /// Priorities that have been synced, along with the time of their last completed sync.
+    pub(crate) fn synced_priorities(
+        &self,
+    ) -> impl Iterator<Item = (StreamPriority, Option<Timestamp>)> + '_ {
+        self.downloading
+            .priority_status
+            .iter()
+            .filter(|status| status.has_synced == Some(true))
+            .map(|status| (status.priority, status.last_synced_at))
+    }
+
     pub fn download_error(&self) -> Option<&PowerSyncError> {
         self.download_error.as_ref()
     }
 
+    /// The error that caused the sync client to stop retrying, after the amount of attempts
+    /// configured in [crate::RetryPolicy::max_attempts] have failed.
+    ///
+    /// The sync client stays disconnected until [crate::PowerSyncDatabase::connect] is called
+    /// again.
+    pub fn fatal_error(&self) -> Option<&PowerSyncError> {
+        self.fatal_error.as_ref()
+    }
+
+    /// Summarizes the connection state of this status as a [ConnectionState].
+    pub fn connection_state(&self) -> ConnectionState {
+        if self.is_connected() {
+            ConnectionState::Connected
+        } else if self.is_connecting() {
+            ConnectionState::Connecting
+        } else if self.download_error.is_some() {
+            ConnectionState::Error
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+
     pub fn is_uploading(&self) -> bool {
         matches!(self.uploads, UploadStatus::Uploading)
     }
 
+    /// Whether there are local writes that haven't been uploaded yet.
+    ///
+    /// Unlike [Self::is_uploading], this is also true while disconnected.
+    pub fn has_pending_uploads(&self) -> bool {
+        self.has_pending_uploads
+    }
+
     pub fn upload_error(&self) -> Option<&PowerSyncError> {
         match self.uploads {
             UploadStatus::Error(ref e) => Some(e),
@@ -109,6 +256,14 @@ impl SyncStatusData {
         }
     }
 
+    /// The download status as reported by the PowerSync core extension.
+    ///
+    /// This is meant for advanced inspection, e.g. for custom debugging dashboards. Most apps
+    /// should use the other getters on [SyncStatusData] instead.
+    pub fn raw_download_status(&self) -> &DownloadSyncStatus {
+        &self.downloading
+    }
+
     /// Status information for a stream, if it's a stream that is currently tracked by the sync
     /// client.
     pub fn for_stream<'a, 'b>(
@@ -150,7 +305,10 @@ impl SyncStatusData {
                 .downloading
                 .downloading
                 .map(|_| stream.progress.clone()),
-            subscription: StreamSubscriptionDescription { core: stream },
+            subscription: StreamSubscriptionDescription {
+                core: stream,
+                now: self.resolved_at.unwrap_or(SystemTime::UNIX_EPOCH),
+            },
         }
     }
 
@@ -172,6 +330,7 @@ impl SyncStatusData {
     pub(crate) fn resolve_offline_state(
         &mut self,
         conn: &SqliteConnection,
+        clock: &dyn Clock,
     ) -> Result<(), PowerSyncError> {
         let stmt = conn.prepare("SELECT powersync_offline_sync_status()")?;
         let ResultCode::ROW = stmt.step()? else {
@@ -179,12 +338,13 @@ impl SyncStatusData {
         };
 
         let raw_status = stmt.column_text(0)?;
-        self.update_from_core(serde_json::from_str(raw_status)?);
+        self.update_from_core(serde_json::from_str(raw_status)?, clock);
         Ok(())
     }
 
-    pub(crate) fn update_from_core(&mut self, core: DownloadSyncStatus) {
+    pub(crate) fn update_from_core(&mut self, core: DownloadSyncStatus, clock: &dyn Clock) {
         self.downloading = Arc::new(core);
+        self.resolved_at = Some(clock.now());
     }
 
     pub(crate) fn set_download_error(&mut self, e: PowerSyncError) {
@@ -192,13 +352,29 @@ impl SyncStatusData {
         self.download_error = Some(e);
     }
 
+    pub(crate) fn set_fatal_error(&mut self, e: Option<PowerSyncError>) {
+        self.fatal_error = e;
+    }
+
     pub(crate) fn set_upload_state(&mut self, state: UploadStatus) {
         self.uploads = state;
     }
 
+    pub(crate) fn set_has_pending_uploads(&mut self, pending: bool) {
+        self.has_pending_uploads = pending;
+    }
+
     pub(crate) fn clear_download_errors(&mut self) {
         self.download_error = None;
     }
+
+    pub(crate) fn did_complete_sync(&self) -> bool {
+        self.did_complete_sync
+    }
+
+    pub(crate) fn set_did_complete_sync(&mut self, completed: bool) {
+        self.did_complete_sync = completed;
+    }
 }
 
 impl Debug for SyncStatusData {
@@ -206,7 +382,10 @@ impl Debug for SyncStatusData {
         f.debug_struct("SyncStatusData")
             .field("downloading", &self.downloading)
             .field("download_error", &self.download_error)
+            .field("fatal_error", &self.fatal_error)
             .field("uploads", &self.uploads)
+            .field("has_pending_uploads", &self.has_pending_uploads)
+            .field("did_complete_sync", &self.did_complete_sync)
             .finish()
     }
 }
@@ -215,3 +394,143 @@ pub struct SyncStreamStatus<'a> {
     pub progress: Option<ProgressCounters>,
     pub subscription: StreamSubscriptionDescription<'a>,
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::{Duration, SystemTime},
+    };
+
+    use serde_json::json;
+
+    use super::{SyncStatus, SyncStatusData};
+    use crate::{StreamPriority, env::Clock, sync::instruction::DownloadSyncStatus};
+
+    struct FixedClock;
+
+    impl Clock for FixedClock {
+        fn now(&self) -> SystemTime {
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1000)
+        }
+    }
+
+    #[test]
+    fn reports_expired_subscriptions() {
+        let subscription = |name: &str, expires_at: i64| {
+            json!({
+                "name": name,
+                "parameters": null,
+                "active": false,
+                "is_default": false,
+                "has_explicit_subscription": false,
+                "expires_at": expires_at,
+                "last_synced_at": null,
+                "progress": {"total": 0, "downloaded": 0},
+            })
+        };
+        let core = serde_json::from_value(json!({
+            "connected": false,
+            "connecting": false,
+            "streams": [subscription("expired", 500), subscription("active", 1500)],
+            "downloading": null,
+        }))
+        .unwrap();
+
+        let mut status = SyncStatusData::default();
+        status.update_from_core(core, &FixedClock);
+
+        let expired: Vec<_> = status
+            .streams()
+            .map(|s| {
+                (
+                    s.subscription.description().name,
+                    s.subscription.is_expired(),
+                )
+            })
+            .collect();
+        assert_eq!(expired, vec![("expired", true), ("active", false)]);
+    }
+
+    #[test]
+    fn parses_download_progress() {
+        let bucket = |priority: i32, at_last: i64, since_last: i64, target_count: i64| {
+            json!({
+                "priority": priority,
+                "at_last": at_last,
+                "since_last": since_last,
+                "target_count": target_count,
+            })
+        };
+        let core: DownloadSyncStatus = serde_json::from_value(json!({
+            "connected": true,
+            "connecting": false,
+            "streams": [],
+            "downloading": {"buckets": {
+                "a": bucket(1, 0, 5, 10),
+                "b": bucket(3, 10, 2, 20),
+            }},
+        }))
+        .unwrap();
+
+        let progress = core.downloading.unwrap();
+        let overall = progress.overall();
+        assert_eq!((overall.downloaded, overall.total), (7, 20));
+
+        let prio_one = progress.for_priority(StreamPriority::ONE);
+        assert_eq!((prio_one.downloaded, prio_one.total), (5, 10));
+    }
+
+    #[test]
+    fn listener_invoked_once_per_update() {
+        let status = SyncStatus::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let listener = status.listener({
+            let calls = calls.clone();
+            move |data| {
+                assert!(data.has_pending_uploads);
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        status.update(|data| data.has_pending_uploads = true);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        status.update(|_| {});
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        drop(listener);
+        status.update(|_| {});
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn listeners_observe_concurrent_updates_in_order() {
+        let status = Arc::new(SyncStatus::new());
+        let last = Arc::new(Mutex::new(None::<Arc<SyncStatusData>>));
+        let _listener = status.listener({
+            let last = last.clone();
+            move |data| *last.lock().unwrap() = Some(data)
+        });
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let status = status.clone();
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        status.update(|data| data.has_pending_uploads = i % 2 == 0);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let last = last.lock().unwrap().clone().unwrap();
+        assert!(Arc::ptr_eq(&last, &status.current_snapshot()));
+    }
+}
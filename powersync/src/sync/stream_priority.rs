@@ -13,12 +13,23 @@ pub struct StreamPriority {
 }
 
 impl StreamPriority {
-    pub const HIGHEST: StreamPriority = StreamPriority { number: 0 };
+    pub const HIGHEST: StreamPriority = StreamPriority::new(0);
 
-    pub const ONE: StreamPriority = StreamPriority { number: 1 };
+    pub const ONE: StreamPriority = StreamPriority::new(1);
+    pub const TWO: StreamPriority = StreamPriority::new(2);
+    pub const THREE: StreamPriority = StreamPriority::new(3);
 
     /// A low priority used to represent fully-completed sync operations across all priorities.
-    pub const SENTINEL: StreamPriority = StreamPriority { number: i32::MAX };
+    pub const SENTINEL: StreamPriority = StreamPriority::new(i32::MAX);
+
+    /// Creates a priority from its protocol number.
+    ///
+    /// This panics for negative numbers, use [StreamPriority::try_from] to validate priorities
+    /// that aren't known to be valid.
+    pub const fn new(number: i32) -> Self {
+        assert!(number >= 0, "Invalid bucket priority value");
+        Self { number }
+    }
 
     pub fn priority_number(self) -> i32 {
         self.into()
@@ -106,3 +117,25 @@ impl Serialize for StreamPriority {
         serializer.serialize_i32(self.number)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::StreamPriority;
+
+    #[test]
+    fn named_priorities() {
+        assert_eq!(StreamPriority::HIGHEST.priority_number(), 0);
+        assert_eq!(StreamPriority::ONE.priority_number(), 1);
+        assert_eq!(StreamPriority::TWO.priority_number(), 2);
+        assert_eq!(StreamPriority::THREE.priority_number(), 3);
+    }
+
+    #[test]
+    fn lower_numbers_are_higher_priorities() {
+        assert!(StreamPriority::HIGHEST > StreamPriority::ONE);
+        assert!(StreamPriority::ONE > StreamPriority::TWO);
+        assert!(StreamPriority::THREE > StreamPriority::SENTINEL);
+        assert_eq!(StreamPriority::try_from(2).unwrap(), StreamPriority::TWO);
+        assert!(StreamPriority::try_from(-1).is_err());
+    }
+}
@@ -4,7 +4,9 @@ use serde::Serialize;
 use serde_with::{DurationSeconds, serde_as};
 
 use crate::{
-    StreamPriority, sync::instruction::ActiveStreamSubscription, util::SerializedJsonObject,
+    StreamPriority,
+    sync::instruction::{ActiveStreamSubscription, StreamError},
+    util::SerializedJsonObject,
 };
 
 #[derive(Debug, Serialize, Hash, PartialEq, Eq, Clone)]
@@ -65,6 +67,8 @@ pub struct SubscribeToStream<'a> {
 /// This includes the [StreamDescription] along with information about the current sync status.
 pub struct StreamSubscriptionDescription<'a> {
     pub(crate) core: &'a ActiveStreamSubscription,
+    /// The time at which the sync status containing this subscription was resolved.
+    pub(crate) now: SystemTime,
 }
 
 impl<'a> StreamSubscriptionDescription<'a> {
@@ -108,6 +112,14 @@ impl<'a> StreamSubscriptionDescription<'a> {
         Some(self.core.expires_at?.into())
     }
 
+    /// Whether this stream subscription has expired at the time the sync status was reported.
+    ///
+    /// Expired subscriptions are no longer synced, unless they're subscribed to again.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= self.now)
+    }
+
     /// Whether this stream subscription has been synced at least once.
     pub fn has_synced(&self) -> bool {
         self.core.last_synced_at.is_some()
@@ -117,6 +129,14 @@ impl<'a> StreamSubscriptionDescription<'a> {
     pub fn last_synced_at(&self) -> Option<SystemTime> {
         Some(self.core.last_synced_at?.into())
     }
+
+    /// Errors reported by the sync service for this stream, e.g. because the subscription used
+    /// invalid parameters.
+    ///
+    /// If this is not empty, the service has rejected the subscription.
+    pub fn errors(&self) -> &[StreamError] {
+        &self.core.errors
+    }
 }
 
 impl<'a> From<&'a StreamSubscriptionDescription<'a>> for StreamDescription<'a> {
@@ -125,3 +145,49 @@ impl<'a> From<&'a StreamSubscriptionDescription<'a>> for StreamDescription<'a> {
     }
 }
 pub struct ChangedSyncSubscriptions(pub Vec<StreamKey>);
+
+#[cfg(test)]
+mod test {
+    use std::time::SystemTime;
+
+    use crate::sync::instruction::ActiveStreamSubscription;
+
+    use super::StreamSubscriptionDescription;
+
+    fn parse_subscription(json: &str) -> ActiveStreamSubscription {
+        serde_json::from_str(json).unwrap()
+    }
+
+    const SUBSCRIPTION: &str = r#"{
+        "name": "a",
+        "parameters": null,
+        "active": true,
+        "is_default": false,
+        "has_explicit_subscription": true,
+        "expires_at": null,
+        "last_synced_at": null,
+        "progress": {"total": 0, "downloaded": 0}"#;
+
+    #[test]
+    fn no_errors_by_default() {
+        let core = parse_subscription(&format!("{SUBSCRIPTION}}}"));
+        let desc = StreamSubscriptionDescription {
+            core: &core,
+            now: SystemTime::UNIX_EPOCH,
+        };
+        assert!(desc.errors().is_empty());
+    }
+
+    #[test]
+    fn reports_stream_errors() {
+        let core = parse_subscription(&format!(
+            r#"{SUBSCRIPTION}, "errors": [{{"message": "invalid parameters"}}]}}"#
+        ));
+        let desc = StreamSubscriptionDescription {
+            core: &core,
+            now: SystemTime::UNIX_EPOCH,
+        };
+        assert_eq!(desc.errors().len(), 1);
+        assert_eq!(desc.errors()[0].message, "invalid parameters");
+    }
+}
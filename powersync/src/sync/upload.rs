@@ -1,7 +1,7 @@
 use std::{collections::HashSet, sync::Arc};
 
 use futures_lite::{
-    FutureExt, StreamExt,
+    FutureExt, Stream, StreamExt,
     future::{self, Boxed},
 };
 use log::{debug, info, warn};
@@ -11,7 +11,7 @@ use crate::db::connection::{SqliteConnection, TransactionGuard};
 use crate::db::watch::ListenerConfiguration;
 use crate::sync::coordinator::SyncCoordinator;
 use crate::{
-    BackendConnector,
+    CrudEntry, SyncOptions,
     db::internal::InnerPowerSyncState,
     error::PowerSyncError,
     sync::{
@@ -21,7 +21,7 @@ use crate::{
 };
 
 pub enum UploadActorCommand {
-    Connect(Arc<dyn BackendConnector>),
+    Connect(SyncOptions),
     TriggerCrudUpload,
     Disconnect,
 }
@@ -44,50 +44,98 @@ impl UploadActor {
     }
 
     pub async fn run(&mut self) {
-        while !self.state.is_stopped() {
-            self.handle_event().await
-        }
+        let monitor = Self::monitor_pending_uploads(self.db.clone());
+        let actor = async {
+            while !self.state.is_stopped() {
+                self.handle_event().await
+            }
+        };
+
+        future::race(actor, monitor).await
     }
 
-    fn connected_state(
-        db: &Arc<InnerPowerSyncState>,
-        connector: Arc<dyn BackendConnector>,
-    ) -> ConnectedUploadActor {
+    fn crud_changes(
+        db: &InnerPowerSyncState,
+        emit_initially: bool,
+    ) -> impl Stream<Item = ()> + Send + 'static {
         let mut tables = HashSet::new();
         tables.insert("ps_crud".to_string());
 
-        let stream = db
-            .env
+        db.env
             .pool
             .update_notifiers()
-            .listen(ListenerConfiguration::if_matches(tables, false));
+            .listen(ListenerConfiguration::if_matches(tables, emit_initially))
+            .map(|_| ())
+    }
+
+    /// Keeps [crate::SyncStatusData::has_pending_uploads] up-to-date, regardless of whether the
+    /// database is connected.
+    async fn monitor_pending_uploads(db: Arc<InnerPowerSyncState>) {
+        let mut changes = Self::crud_changes(&db, true);
+
+        while changes.next().await.is_some() {
+            let pending = async {
+                let reader = db.reader().await?;
+                Ok::<_, PowerSyncError>(
+                    CrudUpload::read_oldest_crud_item_id(reader.sqlite_connection())?.is_some(),
+                )
+            };
+
+            match pending.await {
+                Ok(pending) => {
+                    if db.status.current_snapshot().has_pending_uploads() != pending {
+                        db.status.update(|s| s.set_has_pending_uploads(pending));
+                    }
+                }
+                Err(e) => warn!("Could not check for pending uploads: {e}"),
+            }
+        }
+    }
+
+    fn connected_state(
+        db: &Arc<InnerPowerSyncState>,
+        options: SyncOptions,
+    ) -> ConnectedUploadActor {
+        let stream = Self::crud_changes(db, false);
         ConnectedUploadActor {
-            connector,
-            crud_stream: stream.map(|_| ()).boxed(),
+            options,
+            crud_stream: stream.boxed(),
+            failed_attempts: 0,
         }
     }
 
+    /// Handles a command while an upload is in progress or scheduled.
+    ///
+    /// When `current` options are given, connecting again with the same connector doesn't abort
+    /// the ongoing upload.
     async fn state_transition_from_command_while_uploading(
         commands: &async_channel::Receiver<AsyncRequest<UploadActorCommand>>,
         db: &Arc<InnerPowerSyncState>,
-    ) -> Option<UploadActorState> {
+        current: Option<&SyncOptions>,
+    ) -> CommandWhileUploading {
         match commands.recv().await {
             Ok(command) => match command.command {
                 UploadActorCommand::TriggerCrudUpload => {
                     // Already in progress, don't start another.
-                    None
+                    CommandWhileUploading::Ignore
+                }
+                UploadActorCommand::Connect(options) => {
+                    if current.is_some_and(|current| current.has_same_connector(&options)) {
+                        // Uploads would use the same connector, so there's no need to restart.
+                        CommandWhileUploading::UpdateOptions(options)
+                    } else {
+                        CommandWhileUploading::Transition(UploadActorState::Connected(
+                            Self::connected_state(db, options),
+                        ))
+                    }
                 }
-                UploadActorCommand::Connect(connector) => {
-                    // TODO: Only abort if the connector has changed?
-                    Some(UploadActorState::Connected(Self::connected_state(
-                        db, connector,
-                    )))
+                UploadActorCommand::Disconnect => {
+                    CommandWhileUploading::Transition(UploadActorState::Idle)
                 }
-                UploadActorCommand::Disconnect => Some(UploadActorState::Idle),
             },
             Err(_) => {
                 // There are no remaining instances of the PowerSync database left.
-                Some(UploadActorState::Stopped)
+                CommandWhileUploading::Transition(UploadActorState::Stopped)
             }
         }
     }
@@ -104,9 +152,9 @@ impl UploadActor {
                 };
 
                 match command.command {
-                    UploadActorCommand::Connect(connector) => {
+                    UploadActorCommand::Connect(options) => {
                         let _ = command.response.send(());
-                        UploadActorState::Connected(Self::connected_state(&self.db, connector))
+                        UploadActorState::Connected(Self::connected_state(&self.db, options))
                     }
                     UploadActorCommand::TriggerCrudUpload => {
                         // We can't upload because we're not connector
@@ -138,8 +186,8 @@ impl UploadActor {
                     let _ = command.response.send(());
 
                     match command.command {
-                        UploadActorCommand::Connect(connector) => Transition::Abort(
-                            UploadActorState::Connected(Self::connected_state(&self.db, connector)),
+                        UploadActorCommand::Connect(options) => Transition::Abort(
+                            UploadActorState::Connected(Self::connected_state(&self.db, options)),
                         ),
                         UploadActorCommand::TriggerCrudUpload => Transition::StartUpload,
                         UploadActorCommand::Disconnect => Transition::Abort(UploadActorState::Idle),
@@ -151,18 +199,31 @@ impl UploadActor {
                     Transition::Abort(state) => state,
                 }
             }
-            UploadActorState::RunningUpload { ref mut result } => {
+            UploadActorState::RunningUpload {
+                ref mut result,
+                ref mut options,
+            } => {
                 // A state transition can happen when the current upload is finished or when we
                 // receive a disconnect call.
 
-                let request =
-                    Self::state_transition_from_command_while_uploading(&self.commands, &self.db);
+                let request = Self::state_transition_from_command_while_uploading(
+                    &self.commands,
+                    &self.db,
+                    Some(&*options),
+                );
 
                 let upload_done = async {
-                    let (result, state) = result.await;
+                    let (result, mut state) = result.await;
+                    // Continue with options from connect() calls made during the upload.
+                    state.options = options.clone();
 
                     match result {
                         Ok(_) => {
+                            state.failed_attempts = 0;
+                            self.db
+                                .status
+                                .update(|s| s.set_upload_state(UploadStatus::Idle));
+
                             // It's possible that pending CRUD uploads were preventing data from
                             // syncing. So now that that's completed, notify the download client in
                             // case it needs to retry.
@@ -172,44 +233,82 @@ impl UploadActor {
 
                             // Apart from that, the upload is done and we transition back into the
                             // ready connected state to start the next iteration when needed.
-                            Some(UploadActorState::Connected(state))
+                            CommandWhileUploading::Transition(UploadActorState::Connected(state))
                         }
                         Err(e) => {
+                            state.failed_attempts += 1;
+                            if let Some(observer) = state.options.observer() {
+                                observer.on_error(&e);
+                            }
+
+                            if state
+                                .options
+                                .retry_policy
+                                .is_exhausted(state.failed_attempts)
+                            {
+                                warn!(
+                                    "CRUD uploads failed {} times, not retrying, {e}",
+                                    state.failed_attempts
+                                );
+                                self.db.status.update(|s| {
+                                    s.set_upload_state(UploadStatus::Error(e.clone()));
+                                    s.set_fatal_error(Some(e));
+                                });
+
+                                // Stop uploading until connect() is called again.
+                                return CommandWhileUploading::Transition(UploadActorState::Idle);
+                            }
+
                             warn!("CRUD uploads failed, will retry, {e}");
                             self.db
                                 .status
                                 .update(|s| s.set_upload_state(UploadStatus::Error(e)));
                             let db = self.db.clone();
-
-                            Some(UploadActorState::WaitingForReconnect {
-                                timeout: async move {
-                                    db.sync_iteration_delay().await;
-                                    state
-                                }
-                                .boxed(),
-                            })
+                            let attempt = state.failed_attempts;
+
+                            CommandWhileUploading::Transition(
+                                UploadActorState::WaitingForReconnect {
+                                    timeout: async move {
+                                        db.sync_iteration_delay(attempt).await;
+                                        state
+                                    }
+                                    .boxed(),
+                                },
+                            )
                         }
                     }
                 };
 
-                future::race(request, upload_done)
-                    .await
-                    .unwrap_or(old_state)
+                match future::race(request, upload_done).await {
+                    CommandWhileUploading::Ignore => old_state,
+                    CommandWhileUploading::UpdateOptions(updated) => {
+                        *options = updated;
+                        old_state
+                    }
+                    CommandWhileUploading::Transition(state) => state,
+                }
             }
             UploadActorState::WaitingForReconnect { ref mut timeout } => {
                 // Either the timeout expires, in which case we reconnect, or a disconnect is
                 // requested.
-                let request =
-                    Self::state_transition_from_command_while_uploading(&self.commands, &self.db);
+                let request = Self::state_transition_from_command_while_uploading(
+                    &self.commands,
+                    &self.db,
+                    None,
+                );
 
                 let timeout_expired = async {
                     let state = timeout.await;
-                    Some(UploadActorState::Connected(state))
+                    CommandWhileUploading::Transition(UploadActorState::Connected(state))
                 };
 
-                future::race(request, timeout_expired)
-                    .await
-                    .unwrap_or(old_state)
+                match future::race(request, timeout_expired).await {
+                    CommandWhileUploading::Transition(state) => state,
+                    // Without current options, connect() calls always cause a transition.
+                    CommandWhileUploading::Ignore | CommandWhileUploading::UpdateOptions(_) => {
+                        old_state
+                    }
+                }
             }
             UploadActorState::Stopped => panic!("No further state transitions after stopped"),
         };
@@ -218,12 +317,17 @@ impl UploadActor {
     fn start_upload(&self, state: ConnectedUploadActor) -> UploadActorState {
         let db = self.db.clone();
         UploadActorState::RunningUpload {
+            options: state.options.clone(),
             result: async move {
                 let mut upload = CrudUpload {
-                    connector: state.connector.as_ref(),
+                    options: &state.options,
                     db,
                 };
-                let result = upload.run().await;
+                let result = upload.run();
+                #[cfg(feature = "tracing")]
+                let result =
+                    tracing::Instrument::instrument(result, tracing::info_span!("crud_upload"));
+                let result = result.await;
 
                 (result, state)
             }
@@ -237,6 +341,11 @@ enum UploadActorState {
     Connected(ConnectedUploadActor),
     RunningUpload {
         result: Boxed<(Result<(), PowerSyncError>, ConnectedUploadActor)>,
+        /// The options to use after the `result` future completes.
+        ///
+        /// These start out as a copy of the options used by `result`, but are replaced when
+        /// `connect()` is called with the same connector during the upload.
+        options: SyncOptions,
     },
     WaitingForReconnect {
         timeout: Boxed<ConnectedUploadActor>,
@@ -244,6 +353,17 @@ enum UploadActorState {
     Stopped,
 }
 
+/// The outcome of a command received while an upload is in progress or scheduled.
+enum CommandWhileUploading {
+    /// The command doesn't change the current state.
+    Ignore,
+    /// `connect()` has been called with the same connector, so the current upload continues but
+    /// subsequent uploads use the new options.
+    UpdateOptions(SyncOptions),
+    /// The actor should transition into a new state.
+    Transition(UploadActorState),
+}
+
 impl UploadActorState {
     fn is_stopped(&self) -> bool {
         matches!(self, Self::Stopped)
@@ -251,24 +371,32 @@ impl UploadActorState {
 }
 
 struct ConnectedUploadActor {
-    /// The connector to use when uploading changes.
-    connector: Arc<dyn BackendConnector>,
+    /// The options passed to `connect()`, including the connector to use when uploading changes.
+    options: SyncOptions,
     /// A stream emitting changes when the `ps_crud` table is updated locally.
     crud_stream: futures_lite::stream::Boxed<()>,
+    /// The amount of uploads that have failed since the last successful one, used to apply the
+    /// [crate::RetryPolicy] of [Self::options].
+    failed_attempts: usize,
 }
 
 struct CrudUpload<'a> {
-    connector: &'a dyn BackendConnector,
+    options: &'a SyncOptions,
     db: Arc<InnerPowerSyncState>,
 }
 
 impl<'a> CrudUpload<'a> {
     pub async fn run(&mut self) -> Result<(), PowerSyncError> {
         let mut last_item_id = None::<i64>;
+        let mut batches = 0usize;
 
         while let Some(item) = self.oldest_crud_item_id().await? {
             if last_item_id == Some(item) {
                 warn!("{}", Self::DUPLICATE_ITEM_WARNING);
+                if let Some(entry) = self.read_crud_entry(item).await? {
+                    self.options.connector.on_stuck_crud_item(&entry);
+                }
+
                 return Err(PowerSyncError::argument_error(
                     "Delaying due to previously encountered CRUD item.",
                 ));
@@ -278,7 +406,8 @@ impl<'a> CrudUpload<'a> {
             self.db
                 .status
                 .update(|data| data.set_upload_state(UploadStatus::Uploading));
-            self.connector.upload_data().await?;
+            self.options.connector.upload_data().await?;
+            batches += 1;
         }
 
         // Uploading is completed, advance write checkpoint.
@@ -287,6 +416,9 @@ impl<'a> CrudUpload<'a> {
             advance_target.complete(write_checkpoint, &self.db).await?;
         }
 
+        if let Some(observer) = self.options.observer() {
+            observer.on_upload_complete(batches);
+        }
         Ok(())
     }
 
@@ -296,21 +428,27 @@ impl<'a> CrudUpload<'a> {
     }
 
     async fn get_write_checkpoint(&self) -> Result<i64, PowerSyncError> {
-        let client_id = {
-            let reader = self.db.reader().await?;
+        let client_id = self.db.client_id().await?;
 
-            let stmt = reader
-                .sqlite_connection()
-                .prepare("SELECT powersync_client_id()")?;
-            let ResultCode::ROW = stmt.step()? else {
-                panic!("Expected row"); // Can't happen, scalar select
-            };
+        let credentials = self.options.credentials(self.db.env.clock).await?;
+        write_checkpoint(&self.db, &client_id, credentials, self.options).await
+    }
 
-            stmt.column_text(0)?.to_string()
-        };
+    async fn read_crud_entry(&self, id: i64) -> Result<Option<CrudEntry>, PowerSyncError> {
+        let reader = self.db.reader().await?;
+        let stmt = reader
+            .sqlite_connection()
+            .prepare("SELECT tx_id, data FROM ps_crud WHERE id = ?")?;
+        stmt.bind_int64(1, id)?;
 
-        let credentials = self.connector.fetch_credentials().await?;
-        write_checkpoint(&self.db, &client_id, credentials).await
+        Ok(match stmt.step()? {
+            ResultCode::ROW => Some(CrudEntry::parse(
+                id,
+                stmt.column_int64(0),
+                stmt.column_text(1)?,
+            )?),
+            _ => None,
+        })
     }
 
     fn read_oldest_crud_item_id(conn: &SqliteConnection) -> Result<Option<i64>, PowerSyncError> {
@@ -0,0 +1,96 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use event_listener::Event;
+use futures_lite::future;
+
+use crate::error::{PowerSyncError, RawPowerSyncError};
+
+/// A token that can be used to cancel long-running operations like
+/// [crate::StreamSubscription::wait_for_first_sync] or [crate::PowerSyncDatabase::connect_and_wait].
+///
+/// Clones of a token share their state, so one clone can be passed to [Self::run] while another
+/// one is used to [Self::cancel] the operation.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Event,
+}
+
+impl CancellationToken {
+    /// Creates a token that hasn't been cancelled yet.
+    ///
+    /// This is equivalent to [CancellationToken::default].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all pending and future operations started with [Self::run] on this token.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify(usize::MAX);
+    }
+
+    /// Whether [Self::cancel] has been called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future that completes once this token has been cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            if self.is_cancelled() {
+                return;
+            }
+
+            let listener = self.inner.notify.listen();
+            if self.is_cancelled() {
+                return;
+            }
+            listener.await;
+        }
+    }
+
+    /// Drives the `operation` until it completes or this token is cancelled.
+    ///
+    /// When the token is cancelled first, the operation is dropped and an error with
+    /// [crate::error::PowerSyncErrorKind::Cancelled] is returned.
+    pub async fn run<T>(&self, operation: impl Future<Output = T>) -> Result<T, PowerSyncError> {
+        let cancelled = async {
+            self.cancelled().await;
+            Err(RawPowerSyncError::Cancelled.into())
+        };
+
+        future::or(async { Ok(operation.await) }, cancelled).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_lite::future;
+
+    use super::CancellationToken;
+    use crate::error::PowerSyncErrorKind;
+
+    #[test]
+    fn completes_without_cancellation() {
+        let token = CancellationToken::new();
+        assert_eq!(future::block_on(token.run(async { 1 })).unwrap(), 1);
+    }
+
+    #[test]
+    fn reports_cancellation() {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let result = future::block_on(token.run(future::pending::<()>()));
+        assert_eq!(result.unwrap_err().kind(), PowerSyncErrorKind::Cancelled);
+    }
+}
@@ -1,8 +1,10 @@
 mod bson_split;
+mod cancellation;
 mod line_split;
 mod shared_future;
 
 pub use bson_split::BsonObjects;
+pub use cancellation::CancellationToken;
 pub use line_split::LineSplitter;
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
@@ -10,6 +12,8 @@ use serde_json::value::to_raw_value;
 use serde_json::{Map, Value, value::RawValue};
 pub use shared_future::SharedFuture;
 
+use crate::error::PowerSyncError;
+
 /// A variant of [RawValue] that is guaranteed to be a JSON object.
 #[derive(PartialEq, Eq, Debug, Hash)]
 #[repr(transparent)]
@@ -26,9 +30,22 @@ impl SerializedJsonObject {
         }
     }
 
+    /// Parses `json`, returning an error if it's not a valid JSON object.
+    ///
+    /// Like [Self::from_value], the returned object has its keys sorted and insignificant
+    /// whitespace removed, so equivalent objects have the same representation.
+    pub fn try_from_str(json: &str) -> Result<Box<Self>, PowerSyncError> {
+        let object: Map<String, Value> = serde_json::from_str(json)?;
+        Ok(Self::from_value(&object))
+    }
+
     /// Serializes the given json object and returns its string representation.
+    ///
+    /// Keys of nested objects are sorted, so objects with the same entries have the same
+    /// representation regardless of their insertion order (which is preserved when the
+    /// `preserve_order` feature of `serde_json` is enabled).
     pub fn from_value(value: &Map<String, Value>) -> Box<Self> {
-        let raw = to_raw_value(value).unwrap();
+        let raw = to_raw_value(&SortedKeys::Object(value)).unwrap();
 
         unsafe {
             // Safety: We've just serialized an object.
@@ -37,6 +54,36 @@ impl SerializedJsonObject {
     }
 }
 
+/// Serializes JSON values with object keys in sorted order.
+enum SortedKeys<'a> {
+    Object(&'a Map<String, Value>),
+    Value(&'a Value),
+}
+
+impl Serialize for SortedKeys<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            SortedKeys::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_unstable_by_key(|(key, _)| *key);
+                serializer.collect_map(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, SortedKeys::Value(value))),
+                )
+            }
+            SortedKeys::Value(Value::Object(map)) => SortedKeys::Object(map).serialize(serializer),
+            SortedKeys::Value(Value::Array(items)) => {
+                serializer.collect_seq(items.iter().map(SortedKeys::Value))
+            }
+            SortedKeys::Value(value) => value.serialize(serializer),
+        }
+    }
+}
+
 impl ToOwned for SerializedJsonObject {
     type Owned = Box<Self>;
 
@@ -65,6 +112,8 @@ impl<'de> Deserialize<'de> for Box<SerializedJsonObject> {
     where
         D: serde::Deserializer<'de>,
     {
+        // serde_json skips insignificant whitespace before it starts buffering a raw value, so
+        // the first character tells us whether this is an object.
         let raw = Box::<RawValue>::deserialize(deserializer)?;
         if raw.get().starts_with('{') {
             Ok(unsafe {
@@ -103,3 +152,55 @@ impl AsRef<RawValue> for SerializedJsonObject {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::SerializedJsonObject;
+
+    #[test]
+    fn deserialize_with_leading_whitespace() {
+        let object: Box<SerializedJsonObject> = serde_json::from_str(" \n\t{\"a\":1}").unwrap();
+        let json: &str = (*object).as_ref();
+        assert_eq!(json, r#"{"a":1}"#);
+    }
+
+    #[test]
+    fn from_value_sorts_keys() {
+        let value = json!({"b": 2, "a": [{"d": 1, "c": 0}]});
+        let object = SerializedJsonObject::from_value(value.as_object().unwrap());
+        let json: &str = (*object).as_ref();
+        assert_eq!(json, r#"{"a":[{"c":0,"d":1}],"b":2}"#);
+    }
+
+    #[test]
+    fn try_from_str() {
+        let object = SerializedJsonObject::try_from_str(r#"{"a": [1, 2]}"#).unwrap();
+        let json: &str = (*object).as_ref();
+        assert_eq!(json, r#"{"a":[1,2]}"#);
+
+        assert!(SerializedJsonObject::try_from_str("[1, 2]").is_err());
+        assert!(SerializedJsonObject::try_from_str("\"a\"").is_err());
+        assert!(SerializedJsonObject::try_from_str("{").is_err());
+    }
+
+    #[test]
+    fn try_from_str_sorts_keys() {
+        let object =
+            SerializedJsonObject::try_from_str(r#"{"b": 2, "a": [{"d": 1, "c": 0}]}"#).unwrap();
+        let json: &str = (*object).as_ref();
+        assert_eq!(json, r#"{"a":[{"c":0,"d":1}],"b":2}"#);
+
+        let value = json!({"a": [{"c": 0, "d": 1}], "b": 2});
+        assert_eq!(
+            object,
+            SerializedJsonObject::from_value(value.as_object().unwrap())
+        );
+    }
+
+    #[test]
+    fn deserialize_rejects_other_values() {
+        assert!(serde_json::from_str::<Box<SerializedJsonObject>>("  [1]").is_err());
+    }
+}
@@ -1,9 +1,16 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
 use futures_lite::{StreamExt, future};
-use powersync::PowerSyncDatabase;
+use powersync::error::PowerSyncError;
 use powersync::schema::{
     Column, PendingStatement, PendingStatementValue, RawTable, RawTableSchema, Schema, Table,
     TrackPreviousValues,
 };
+use powersync::{
+    BackendConnector, CrudTransaction, PowerSyncCredentials, PowerSyncDatabase,
+    TransactionConnector, TransactionUploader,
+};
 use powersync_test_utils::{DatabaseTest, execute, query_all};
 use rusqlite::params;
 use serde_json::{Value, json};
@@ -37,6 +44,67 @@ fn include_metadata() {
     })
 }
 
+#[test]
+fn execute_with_metadata() {
+    future::block_on(async move {
+        let test = DatabaseTest::new();
+        let db = PowerSyncDatabase::new(test.in_memory(), {
+            let mut schema = Schema::default();
+            schema
+                .tables
+                .push(Table::create("lists", vec![Column::text("name")], |tbl| {
+                    tbl.options.track_metadata = true
+                }));
+            schema.tables.push(Table::create(
+                "untracked",
+                vec![Column::text("name")],
+                |_| {},
+            ));
+            schema
+        });
+
+        db.execute_with_metadata(
+            "INSERT INTO lists (id, name) VALUES (uuid(), ?)",
+            params!["entry"],
+            "so meta",
+        )
+        .await
+        .unwrap();
+
+        let batch = db.next_crud_transaction().await.unwrap().unwrap();
+        assert_eq!(batch.crud[0].metadata, Some("so meta".to_string()));
+        batch.complete().await.unwrap();
+
+        // Metadata written by the statement takes precedence.
+        db.execute_with_metadata(
+            "INSERT INTO lists (id, name, _metadata) VALUES (uuid(), ?, ?)",
+            params!["entry", "explicit"],
+            "so meta",
+        )
+        .await
+        .unwrap();
+
+        let batch = db.next_crud_transaction().await.unwrap().unwrap();
+        assert_eq!(batch.crud[0].metadata, Some("explicit".to_string()));
+        batch.complete().await.unwrap();
+
+        // Tables without metadata should reject the write.
+        assert!(
+            db.execute_with_metadata(
+                "INSERT INTO untracked (id, name) VALUES (uuid(), ?)",
+                params!["entry"],
+                "so meta",
+            )
+            .await
+            .is_err()
+        );
+        assert_eq!(
+            query_all(&db, "SELECT * FROM untracked", params![]).await,
+            json!([])
+        );
+    })
+}
+
 #[test]
 fn include_old_values() {
     future::block_on(async move {
@@ -191,6 +259,38 @@ fn ignore_empty_update() {
     })
 }
 
+#[test]
+fn execute_tracked_reports_crud() {
+    future::block_on(async move {
+        let test = DatabaseTest::new();
+        let db = PowerSyncDatabase::new(test.in_memory(), {
+            let mut schema = Schema::default();
+            schema
+                .tables
+                .push(Table::create("lists", vec![Column::text("name")], |tbl| {
+                    tbl.options.ignore_empty_updates = true
+                }));
+            schema
+        });
+
+        let result = db
+            .execute_tracked(
+                "INSERT INTO lists (id, name) VALUES (uuid(), ?)",
+                params!["entry"],
+            )
+            .await
+            .unwrap();
+        assert!(result.crud_generated);
+
+        // Updating to the same value should not create a CRUD entry.
+        let result = db
+            .execute_tracked("UPDATE lists SET name = ?", params!["entry"])
+            .await
+            .unwrap();
+        assert!(!result.crud_generated);
+    })
+}
+
 #[test]
 fn insert() {
     future::block_on(async move {
@@ -219,6 +319,111 @@ fn insert() {
         };
         assert_eq!(tx.id, Some(1));
         assert_eq!(tx.crud.len(), 1);
+        assert_eq!(tx.crud[0].raw_data.as_deref(), Some(r#"{"name":"name"}"#));
+        assert_eq!(tx.crud[0].raw_previous_values, None);
+    });
+}
+
+#[test]
+fn crud_entries() {
+    future::block_on(async move {
+        let test = DatabaseTest::new();
+        let db = test.in_memory_database();
+
+        for amount in [5, 100, 50] {
+            let mut writer = db.writer().await.unwrap();
+            let writer = writer.transaction().unwrap();
+            for _ in 0..amount {
+                writer
+                    .execute("INSERT INTO users (id) VALUES (uuid())", params![])
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+        }
+
+        let entries: Vec<_> = db.crud_entries().try_collect().await.unwrap();
+        assert_eq!(entries.len(), 155);
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.client_id, i as i64 + 1);
+        }
+        assert_eq!(entries[4].transaction_id, entries[0].transaction_id);
+        assert_ne!(entries[5].transaction_id, entries[4].transaction_id);
+
+        db.complete_crud_entries(entries[9].client_id, None)
+            .await
+            .unwrap();
+        let mut remaining = db.crud_entries();
+        let next = remaining.try_next().await.unwrap().unwrap();
+        assert_eq!(next.client_id, 11);
+    });
+}
+
+#[test]
+fn stale_write_checkpoint() {
+    future::block_on(async move {
+        let test = DatabaseTest::new();
+        let db = test.in_memory_database();
+        let target_op = async || {
+            query_all(
+                &db,
+                "SELECT target_op FROM ps_buckets WHERE name = '$local'",
+                params![],
+            )
+            .await
+        };
+
+        execute(&db, "INSERT INTO users (id) VALUES (uuid())", params![]).await;
+        let tx = db.next_crud_transaction().await.unwrap().unwrap();
+        tx.complete_with_checkpoint(10).await.unwrap();
+        assert_eq!(target_op().await, json!([{"target_op": 10}]));
+
+        // Local writes reset the target op, but completing with an older checkpoint without new
+        // writes in between must not move it backwards.
+        db.complete_crud_entries(0, Some(5)).await.unwrap();
+        assert_eq!(target_op().await, json!([{"target_op": 10}]));
+    });
+}
+
+#[test]
+fn transaction_connector() {
+    struct CountingUploader(Arc<Mutex<Vec<usize>>>);
+
+    #[async_trait]
+    impl TransactionUploader for CountingUploader {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            unreachable!()
+        }
+
+        async fn upload_transaction(
+            &self,
+            transaction: &CrudTransaction<'_>,
+        ) -> Result<(), PowerSyncError> {
+            self.0.lock().unwrap().push(transaction.crud.len());
+            Ok(())
+        }
+    }
+
+    future::block_on(async move {
+        let test = DatabaseTest::new();
+        let db = test.in_memory_database();
+
+        for amount in [1, 2, 3] {
+            let mut writer = db.writer().await.unwrap();
+            let writer = writer.transaction().unwrap();
+            for _ in 0..amount {
+                writer
+                    .execute("INSERT INTO users (id) VALUES (uuid())", params![])
+                    .unwrap();
+            }
+            writer.commit().unwrap();
+        }
+
+        let uploads = Arc::new(Mutex::new(vec![]));
+        let connector = TransactionConnector::new(db.clone(), CountingUploader(uploads.clone()));
+        connector.upload_data().await.unwrap();
+
+        assert_eq!(*uploads.lock().unwrap(), vec![1, 2, 3]);
+        assert!(db.next_crud_transaction().await.unwrap().is_none());
     });
 }
 
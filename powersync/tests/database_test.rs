@@ -1,12 +1,17 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
 use async_oneshot::oneshot;
 use futures_lite::{StreamExt, future};
-use powersync::PowerSyncDatabase;
-use powersync::error::PowerSyncError;
+use powersync::env::PowerSyncEnvironment;
+use powersync::error::{PowerSyncError, PowerSyncErrorKind};
 use powersync::schema::{Column, Schema, Table};
-use powersync_test_utils::{DatabaseTest, UserRow, execute, query_all};
+use powersync::{ConnectionPool, PoolOptions, PowerSyncDatabase};
+use powersync_test_utils::{DatabaseTest, ImmediateTimer, UserRow, execute, query_all};
 use rusqlite::params;
+use serde::Deserialize;
 use serde_json::value::RawValue;
 use serde_json::{Value, json};
 
@@ -22,6 +27,443 @@ fn link_core_extension() {
     });
 }
 
+#[test]
+fn local_only_database() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let env = PowerSyncEnvironment::local_only(ConnectionPool::single_connection(conn));
+    let db = PowerSyncDatabase::new(env, DatabaseTest::default_schema());
+
+    future::block_on(async move {
+        execute(
+            &db,
+            "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+            params!["steven", "steven@journeyapps.com"],
+        )
+        .await;
+
+        let rows = query_all(&db, "SELECT name FROM users", params![]).await;
+        assert_eq!(rows, json!([{"name": "steven"}]));
+    });
+}
+
+#[test]
+fn local_only_lease_timeout() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let env = PowerSyncEnvironment::local_only(ConnectionPool::single_connection(conn));
+    let db = PowerSyncDatabase::new(env, DatabaseTest::default_schema());
+
+    future::block_on(async move {
+        // With a single connection, readers have to wait for the writer.
+        let writer = db.writer().await.unwrap();
+        let Err(_) = db.reader_timeout(Duration::from_millis(10)).await else {
+            panic!("Expected lease to time out");
+        };
+
+        drop(writer);
+        db.reader_timeout(Duration::from_secs(10)).await.unwrap();
+    });
+}
+
+#[test]
+fn blocking_reader_after_async_initialization() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        execute(
+            &db,
+            "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+            params!["steven", "steven@journeyapps.com"],
+        )
+        .await
+    });
+
+    let reader = db.reader_blocking().unwrap();
+    let name: String = reader
+        .query_one("SELECT name FROM users", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(name, "steven");
+}
+
+#[test]
+fn blocking_writer_initializes_database() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    // The users view only exists after the database has been initialized.
+    let writer = db.writer_blocking().unwrap();
+    writer
+        .execute(
+            "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+            params!["steven", "steven@journeyapps.com"],
+        )
+        .unwrap();
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_api() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    let writer = db.blocking_writer().unwrap();
+    writer
+        .execute(
+            "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+            params!["steven", "steven@journeyapps.com"],
+        )
+        .unwrap();
+    drop(writer);
+
+    let reader = db.blocking_reader().unwrap();
+    let name: String = reader
+        .query_one("SELECT name FROM users", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(name, "steven");
+    drop(reader);
+
+    let tx = db.blocking_next_crud_transaction().unwrap().unwrap();
+    assert_eq!(tx.crud.len(), 1);
+}
+
+#[test]
+fn detects_schema_changes() {
+    let test = DatabaseTest::new();
+    let open = |schema: Schema| PowerSyncDatabase::new(test.in_test_dir(), schema);
+
+    future::block_on(async {
+        let db = open(DatabaseTest::default_schema());
+        assert!(db.schema_changed_since_last_open().await.unwrap());
+        drop(db);
+
+        let db = open(DatabaseTest::default_schema());
+        assert!(!db.schema_changed_since_last_open().await.unwrap());
+        drop(db);
+
+        let mut schema = DatabaseTest::default_schema();
+        schema
+            .tables
+            .push(Table::create("lists", vec![Column::text("name")], |_| {}));
+        let db = open(schema);
+        assert!(db.schema_changed_since_last_open().await.unwrap());
+    });
+}
+
+#[test]
+fn clear_local_table() {
+    let test = DatabaseTest::new();
+    let mut schema = DatabaseTest::default_schema();
+    schema
+        .tables
+        .push(Table::create("cache", vec![Column::text("value")], |tbl| {
+            tbl.options.local_only = true
+        }));
+    let db = PowerSyncDatabase::new(test.in_memory(), schema);
+
+    future::block_on(async {
+        for value in ["a", "b"] {
+            execute(
+                &db,
+                "INSERT INTO cache (id, value) VALUES (uuid(), ?)",
+                params![value],
+            )
+            .await;
+        }
+
+        assert_eq!(db.clear_local_table("cache").await.unwrap(), 2);
+        assert_eq!(
+            query_all(&db, "SELECT * FROM cache", params![]).await,
+            json!([])
+        );
+
+        // Synced tables can't be cleared this way.
+        assert!(db.clear_local_table("users").await.is_err());
+    });
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid7_is_time_ordered() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    future::block_on(async {
+        let mut ids = vec![];
+        for _ in 0..10 {
+            let reader = db.reader().await.unwrap();
+            let id: String = reader
+                .query_one("SELECT uuid7()", params![], |row| row.get(0))
+                .unwrap();
+            ids.push(id);
+        }
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        assert!(ids.iter().all(|id| id.as_bytes()[14] == b'7'));
+    });
+}
+
+#[test]
+fn execute_returns_affected_rows() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        db.execute("CREATE TABLE local (value INTEGER)", params![])
+            .await
+            .unwrap();
+        let inserted = db
+            .execute("INSERT INTO local VALUES (1), (2), (3)", params![])
+            .await
+            .unwrap();
+        assert_eq!(inserted, 3);
+
+        let updated = db
+            .execute("UPDATE local SET value = value + 1", params![])
+            .await
+            .unwrap();
+        assert_eq!(updated, 3);
+    });
+}
+
+#[test]
+fn query_one_and_opt() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        let version: String = db
+            .query_one("SELECT powersync_rs_version()", params![], |row| row.get(0))
+            .await
+            .unwrap();
+        assert!(!version.is_empty());
+
+        let missing: Option<String> = db
+            .query_opt("SELECT name FROM users", params![], |row| row.get(0))
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+
+        assert!(
+            db.query_one("SELECT name FROM users", params![], |row| row
+                .get::<_, String>(0))
+                .await
+                .is_err()
+        );
+    });
+}
+
+#[test]
+fn limits_concurrent_readers() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let test = DatabaseTest::new();
+    let pool = ConnectionPool::open(test.dir.path().join("test.db"))
+        .unwrap()
+        .with_max_concurrent_readers(2);
+
+    future::block_on(async {
+        let a = pool.reader().await;
+        let b = pool.reader().await;
+        assert_eq!(pool.readers_in_use(), 2);
+
+        // The pool has more connections, but the limit should prevent leasing them.
+        assert!(future::poll_once(pool.reader()).await.is_none());
+
+        drop(a);
+        assert_eq!(pool.readers_in_use(), 1);
+        let _c = pool.reader().await;
+        drop(b);
+    });
+}
+
+#[test]
+fn lease_timeout() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        // In-memory databases use a single connection, so holding the writer blocks readers.
+        let writer = db.writer().await.unwrap();
+        assert!(db.reader_timeout(Duration::from_secs(1)).await.is_err());
+        assert!(db.writer_timeout(Duration::from_secs(1)).await.is_err());
+
+        drop(writer);
+        db.writer_timeout(Duration::from_secs(1)).await.unwrap();
+    });
+}
+
+#[test]
+fn pool_update_notifications() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let pool = ConnectionPool::single_connection(conn);
+    let mut updates = pool.updates();
+
+    future::block_on(async {
+        {
+            let writer = pool.writer().await;
+            writer
+                .execute("CREATE TABLE foo (bar TEXT)", params![])
+                .unwrap();
+            writer
+                .execute("INSERT INTO foo (bar) VALUES (?)", params!["baz"])
+                .unwrap();
+        }
+
+        let notification = updates.next().await.unwrap();
+        assert!(notification.tables().contains("foo"));
+    });
+}
+
+#[test]
+fn minimal_pool_isolation() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let test = DatabaseTest::new();
+    let pool = ConnectionPool::open_minimal(test.dir.path().join("minimal.db")).unwrap();
+    let mut updates = pool.updates();
+
+    future::block_on(async {
+        {
+            let writer = pool.writer().await;
+            writer
+                .execute("CREATE TABLE foo (bar TEXT)", params![])
+                .unwrap();
+        }
+
+        {
+            let mut writer = pool.writer().await;
+            let tx = writer.transaction().unwrap();
+            tx.execute("INSERT INTO foo (bar) VALUES (?)", params!["baz"])
+                .unwrap();
+
+            // The reader is a separate connection, so it shouldn't see uncommitted writes.
+            let reader = pool.reader().await;
+            let count: i64 = reader
+                .query_one("SELECT COUNT(*) FROM foo", params![], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 0);
+
+            tx.commit().unwrap();
+        }
+
+        let notification = updates.next().await.unwrap();
+        assert!(notification.tables().contains("foo"));
+
+        let reader = pool.reader().await;
+        let count: i64 = reader
+            .query_one("SELECT COUNT(*) FROM foo", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    });
+}
+
+#[test]
+fn reader_fresh_sees_committed_writes() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    future::block_on(async move {
+        {
+            // Leave a read transaction open on a reader returned to the pool.
+            let reader = db.reader().await.unwrap();
+            reader.execute_batch("BEGIN").unwrap();
+            let _: i64 = reader
+                .query_one("SELECT COUNT(*) FROM users", params![], |row| row.get(0))
+                .unwrap();
+        }
+
+        execute(
+            &db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["Test"],
+        )
+        .await;
+
+        for _ in 0..5 {
+            let reader = db.reader_fresh().await.unwrap();
+            let count: i64 = reader
+                .query_one("SELECT COUNT(*) FROM users", params![], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+    });
+}
+
+#[test]
+fn pool_from_existing_writer() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let test = DatabaseTest::new();
+    let path = test.dir.path().join("existing.db");
+
+    let writer = rusqlite::Connection::open(&path).unwrap();
+    writer.pragma_update(None, "journal_mode", "WAL").unwrap();
+    writer.pragma_update(None, "user_version", 42).unwrap();
+    writer.pragma_update(None, "cache_size", -1234).unwrap();
+
+    let pool = ConnectionPool::from_writer_and_path(writer, &path, 2).unwrap();
+    future::block_on(async {
+        {
+            let writer = pool.writer().await;
+            let cache_size: i64 = writer
+                .query_one("PRAGMA cache_size", params![], |row| row.get(0))
+                .unwrap();
+            assert_eq!(cache_size, -1234);
+        }
+
+        let reader = pool.reader().await;
+        let version: i64 = reader
+            .query_one("PRAGMA user_version", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, 42);
+    });
+}
+
+#[test]
+fn attach_database() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    let reference = test.dir.path().join("reference.db");
+    {
+        let conn = rusqlite::Connection::open(&reference).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE countries (code TEXT); INSERT INTO countries VALUES ('de'), ('nl');",
+        )
+        .unwrap();
+    }
+
+    future::block_on(async move {
+        db.attach("reference", &reference).await.unwrap();
+
+        for _ in 0..6 {
+            // Check all readers see the attached database.
+            let reader = db.reader().await.unwrap();
+            let count: i64 = reader
+                .query_one(
+                    "SELECT COUNT(*) FROM reference.countries",
+                    params![],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 2);
+        }
+
+        db.detach("reference").await.unwrap();
+        for _ in 0..6 {
+            let reader = db.reader().await.unwrap();
+            assert!(
+                reader
+                    .prepare("SELECT COUNT(*) FROM reference.countries")
+                    .is_err()
+            );
+        }
+    });
+}
+
 #[test]
 fn test_concurrent_reads() {
     let test = DatabaseTest::new();
@@ -87,6 +529,37 @@ fn test_concurrent_reads() {
     });
 }
 
+#[test]
+fn coalesced_table_updates() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let db = test.test_dir_database();
+    let mut noop = Context::from_waker(Waker::noop());
+
+    let mut stream = db
+        .watch_tables_coalesced(false, ["users"], Duration::from_millis(10))
+        .boxed();
+    assert_eq!(stream.poll_next(&mut noop), Poll::Pending);
+
+    let insert = |name: &str| {
+        future::block_on(execute(
+            &db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params![name],
+        ))
+    };
+
+    insert("a");
+    // The stream has seen the first write and is now waiting for the coalescing window.
+    assert_eq!(stream.poll_next(&mut noop), Poll::Pending);
+    insert("b");
+    insert("c");
+
+    // All three writes should be reported as a single event.
+    assert_eq!(stream.poll_next(&mut noop), Poll::Ready(Some(())));
+    assert_eq!(stream.poll_next(&mut noop), Poll::Pending);
+}
+
 #[test]
 fn test_table_updates() {
     let test = DatabaseTest::new();
@@ -156,6 +629,36 @@ fn test_table_updates() {
     });
 }
 
+#[test]
+fn rollback_does_not_leak_into_next_lease() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    future::block_on(async move {
+        execute(&db, "CREATE TABLE unrelated (foo TEXT)", params![]).await;
+        let mut stream = db.watch_all_updates();
+
+        {
+            let mut writer = db.writer().await.unwrap();
+            let writer = writer.transaction().unwrap();
+            writer
+                .execute(
+                    "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+                    params!["rolled back"],
+                )
+                .unwrap();
+            writer.rollback().unwrap();
+        }
+
+        execute(&db, "INSERT INTO unrelated (foo) VALUES ('bar')", params![]).await;
+
+        let updates = stream.next().await.unwrap();
+        assert!(updates.contains("unrelated"));
+        assert!(!updates.contains("ps_data__users"));
+        assert!(!updates.contains("ps_crud"));
+    });
+}
+
 #[test]
 fn test_watch_statement() {
     let test = DatabaseTest::new();
@@ -263,3 +766,438 @@ fn test_external_schema() {
         assert_eq!(rows, json!([{"name": "User"}]));
     });
 }
+
+#[test]
+fn client_id() {
+    let test = DatabaseTest::new();
+
+    future::block_on(async {
+        let id = test.test_dir_database().client_id().await.unwrap();
+        assert!(!id.is_empty());
+
+        // The id should be stable, even across database instances.
+        let db = test.test_dir_database();
+        assert_eq!(db.client_id().await.unwrap(), id);
+        assert_eq!(db.client_id().await.unwrap(), id);
+    });
+}
+
+#[test]
+fn lease_multiple_readers() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    future::block_on(async {
+        let readers = db.readers(3).await.unwrap();
+        assert_eq!(readers.len(), 3);
+
+        let results = readers
+            .iter()
+            .map(|reader| reader.query_row("SELECT 1", params![], |row| row.get::<_, i64>(0)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(results, vec![1, 1, 1]);
+
+        // The pool has five readers, so only two more are available.
+        assert_eq!(db.readers(5).await.unwrap().len(), 2);
+
+        drop(readers);
+        assert_eq!(db.readers(5).await.unwrap().len(), 5);
+    });
+}
+
+#[test]
+fn cached_queries() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    future::block_on(async {
+        db.execute(
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["Test user"],
+        )
+        .await
+        .unwrap();
+
+        for _ in 0..10 {
+            let name = db
+                .query_one_cached("SELECT name FROM users", params![], |row| {
+                    row.get::<_, String>(0)
+                })
+                .await
+                .unwrap();
+            assert_eq!(name, "Test user");
+        }
+
+        let missing = db
+            .query_opt_cached(
+                "SELECT name FROM users WHERE name = ?",
+                params!["nobody"],
+                |row| row.get::<_, String>(0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    });
+}
+
+#[test]
+fn configures_statement_cache_capacity() {
+    /// Counts the prepared statements that haven't been finalized on `conn`.
+    fn open_statements(conn: &rusqlite::Connection) -> usize {
+        let db = unsafe { conn.handle() };
+        let mut stmt = std::ptr::null_mut();
+        let mut count = 0;
+        loop {
+            stmt = unsafe { rusqlite::ffi::sqlite3_next_stmt(db, stmt) };
+            if stmt.is_null() {
+                return count;
+            }
+            count += 1;
+        }
+    }
+
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let test = DatabaseTest::new();
+    let mut options = PoolOptions::default();
+    options.with_reader_count(1);
+    options.with_statement_cache_capacity(2);
+    let pool =
+        ConnectionPool::open_with_options(test.dir.path().join("test.db"), &options).unwrap();
+
+    future::block_on(async {
+        let reader = pool.reader().await;
+        let initial = open_statements(&reader);
+
+        // Cached statements are kept alive after being dropped.
+        drop(reader.prepare_cached("SELECT 1").unwrap());
+        drop(reader.prepare_cached("SELECT 1").unwrap());
+        assert_eq!(open_statements(&reader), initial + 1);
+
+        // Statements are evicted once the capacity has been reached.
+        drop(reader.prepare_cached("SELECT 2").unwrap());
+        drop(reader.prepare_cached("SELECT 3").unwrap());
+        assert_eq!(open_statements(&reader), initial + 2);
+
+        reader.flush_prepared_statement_cache();
+        assert_eq!(open_statements(&reader), initial);
+    });
+
+    // Without caching, statements are finalized when they're dropped.
+    options.with_statement_cache_capacity(0);
+    let pool =
+        ConnectionPool::open_with_options(test.dir.path().join("test.db"), &options).unwrap();
+    future::block_on(async {
+        let writer = pool.writer().await;
+        let initial = open_statements(&writer);
+        drop(writer.prepare_cached("SELECT 1").unwrap());
+        assert_eq!(open_statements(&writer), initial);
+    });
+}
+
+#[test]
+fn table_change_callback() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let listener = db.on_tables_changed(["users"], {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    future::block_on(async {
+        let insert_user = async || {
+            db.execute(
+                "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+                params!["Test", "test@example.org"],
+            )
+            .await
+            .unwrap();
+        };
+
+        insert_user().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        drop(listener);
+        insert_user().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn table_change_callback_drops_listener() {
+    use std::sync::Mutex;
+
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let registered = Arc::new(Mutex::new(None));
+
+    // A callback that unregisters itself after the first invocation.
+    let listener = db.on_tables_changed(["users"], {
+        let calls = calls.clone();
+        let registered = registered.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            drop(registered.lock().unwrap().take());
+        }
+    });
+    *registered.lock().unwrap() = Some(listener);
+
+    future::block_on(async {
+        for _ in 0..2 {
+            db.execute(
+                "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+                params!["Test", "test@example.org"],
+            )
+            .await
+            .unwrap();
+        }
+    });
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn execute_checked_rejects_writes_to_insert_only_tables() {
+    let test = DatabaseTest::new();
+    let mut schema = DatabaseTest::default_schema();
+    schema.tables.push(Table::create(
+        "logs",
+        vec![Column::text("message")],
+        |tbl| tbl.options.insert_only = true,
+    ));
+    let db = PowerSyncDatabase::new(test.in_memory(), schema);
+
+    future::block_on(async {
+        db.execute_checked(
+            "INSERT INTO logs (id, message) VALUES (uuid(), ?)",
+            params!["hello"],
+        )
+        .await
+        .unwrap();
+
+        for sql in ["UPDATE logs SET message = 'changed'", "DELETE FROM logs"] {
+            let err = db.execute_checked(sql, params![]).await.unwrap_err();
+            assert_eq!(err.kind(), PowerSyncErrorKind::InvalidArgument);
+        }
+
+        // Other tables are not affected.
+        db.execute_checked("DELETE FROM users", params![])
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn get_row_by_id() {
+    let test = DatabaseTest::new();
+    let mut schema = DatabaseTest::default_schema();
+    schema.tables.push(Table::create(
+        "accounts",
+        vec![Column::text("name")],
+        |tbl| tbl.view_name_override = Some("people".into()),
+    ));
+    let db = PowerSyncDatabase::new(test.in_memory(), schema);
+
+    future::block_on(async {
+        execute(
+            &db,
+            "INSERT INTO people (id, name) VALUES (?, ?)",
+            params!["a", "Test user"],
+        )
+        .await;
+
+        let name = |row: &rusqlite::Row| row.get::<_, String>("name");
+        assert_eq!(
+            db.get_row("accounts", "a", name).await.unwrap(),
+            Some("Test user".to_string())
+        );
+        assert_eq!(db.get_row("accounts", "b", name).await.unwrap(), None);
+
+        let err = db.get_row("people", "a", name).await.unwrap_err();
+        assert_eq!(err.kind(), PowerSyncErrorKind::InvalidArgument);
+    });
+}
+
+#[test]
+fn query_as_deserializes_rows() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: String,
+        name: String,
+        email: String,
+        photo_id: Option<String>,
+    }
+
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        execute(
+            &db,
+            "INSERT INTO users (id, name, email) VALUES (?, ?, ?)",
+            params!["a", "Test user", "test@example.org"],
+        )
+        .await;
+
+        let users: Vec<User> = db.query_as("SELECT * FROM users", params![]).await.unwrap();
+        assert_eq!(
+            users,
+            vec![User {
+                id: "a".to_string(),
+                name: "Test user".to_string(),
+                email: "test@example.org".to_string(),
+                photo_id: None,
+            }]
+        );
+
+        // Rows that don't match the target type are reported as errors.
+        assert!(
+            db.query_as::<User, _>("SELECT id FROM users", params![])
+                .await
+                .is_err()
+        );
+    });
+}
+
+#[test]
+fn query_page() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(async {
+        for i in 0..5 {
+            execute(
+                &db,
+                "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+                params![format!("user {i}"), "test@example.org"],
+            )
+            .await;
+        }
+
+        let params = params!["test@example.org"];
+        let page = |limit, offset| {
+            db.query_page(
+                "SELECT name FROM users WHERE email = ? ORDER BY name",
+                params,
+                limit,
+                offset,
+                |row| row.get::<_, String>(0),
+            )
+        };
+
+        let first = page(2, 0).await.unwrap();
+        assert_eq!(first.items, vec!["user 0", "user 1"]);
+        assert!(first.has_more);
+
+        let last = page(2, 4).await.unwrap();
+        assert_eq!(last.items, vec!["user 4"]);
+        assert!(!last.has_more);
+
+        // A page ending exactly at the last row has no more rows after it.
+        let exact = page(3, 2).await.unwrap();
+        assert_eq!(exact.items, vec!["user 2", "user 3", "user 4"]);
+        assert!(!exact.has_more);
+
+        // Queries with their own LIMIT clause are paginated within that limit.
+        let limited = db
+            .query_page(
+                "SELECT name FROM users ORDER BY name LIMIT 3;",
+                &[],
+                2,
+                2,
+                |row| row.get::<_, String>(0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(limited.items, vec!["user 2"]);
+        assert!(!limited.has_more);
+    });
+}
+
+#[test]
+fn watch_count() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+    let mut counts = db.watch_count(["users"], "SELECT count(*) FROM users".to_string(), []);
+
+    future::block_on(async {
+        assert_eq!(counts.next().await.unwrap().unwrap(), 0);
+
+        for expected in 1..=2 {
+            execute(
+                &db,
+                "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+                params!["Test user", "test@example.org"],
+            )
+            .await;
+            assert_eq!(counts.next().await.unwrap().unwrap(), expected);
+        }
+
+        execute(&db, "DELETE FROM users", params![]).await;
+        assert_eq!(counts.next().await.unwrap().unwrap(), 0);
+    });
+}
+
+#[test]
+fn core_version() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    let version = future::block_on(db.core_version()).unwrap();
+    version.validate().unwrap();
+    assert!(version.build_hash.is_some());
+}
+
+#[cfg(feature = "dynamic-extension")]
+#[test]
+fn open_with_missing_extension() {
+    let test = DatabaseTest::new();
+    let result = ConnectionPool::open_with_extension(
+        test.dir.path().join("test.db"),
+        test.dir.path().join("missing_extension"),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn verify_core() {
+    PowerSyncEnvironment::powersync_auto_extension().unwrap();
+    let version = PowerSyncEnvironment::verify_core().unwrap();
+    version.validate().unwrap();
+}
+
+#[test]
+fn close_without_running_tasks() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+
+    // Dropping the tasks without polling them must not make close() wait for them.
+    drop(db.async_tasks());
+
+    future::block_on(async move {
+        let reader = db.reader().await.unwrap();
+        db.close().await;
+
+        // The database can no longer be used, but returning leased connections is fine.
+        let Err(e) = db.writer().await else {
+            panic!("Expected writer lease to fail after close()");
+        };
+        assert_eq!(e.to_string(), "The database has been closed");
+        assert!(db.reader().await.is_err());
+        drop(reader);
+    });
+}
+
+#[test]
+#[should_panic(expected = "Actor has not been registered")]
+fn disconnect_without_tasks() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    future::block_on(db.disconnect());
+}
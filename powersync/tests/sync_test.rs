@@ -1,15 +1,21 @@
 use async_task::Task;
+use async_trait::async_trait;
 use futures_lite::{StreamExt, future};
 use powersync::{
-    PowerSyncDatabase, StreamPriority, StreamSubscription, StreamSubscriptionOptions, SyncOptions,
-    SyncStatusData, error::PowerSyncError,
+    ActiveStreamSubscription, BackendConnector, CancellationToken, ConnectionState, CrudEntry,
+    CrudTransaction, PowerSyncCredentials, PowerSyncDatabase, StreamPriority, StreamSubscription,
+    StreamSubscriptionOptions, SyncOptions, SyncStatusData, TransactionConnector,
+    TransactionUploader,
+    error::{PowerSyncError, PowerSyncErrorKind},
 };
 use powersync_test_utils::{
-    DatabaseTest,
-    mock_sync_service::TestConnector,
-    sync_line::{Checkpoint, SyncLine},
+    DatabaseTest, ImmediateTimer, execute,
+    mock_sync_service::{TestConnector, WriteCheckpointResponse},
+    sync_line::{BucketChecksum, BucketSubscriptionReason, Checkpoint, SyncLine},
 };
-use serde_json::json;
+use rusqlite::params;
+use serde_json::{Value, json};
+use std::time::Duration;
 
 struct SyncStreamTest {
     test: DatabaseTest,
@@ -19,7 +25,10 @@ struct SyncStreamTest {
 
 impl SyncStreamTest {
     fn new() -> Self {
-        let test = DatabaseTest::new();
+        Self::with_test(DatabaseTest::new())
+    }
+
+    fn with_test(test: DatabaseTest) -> Self {
         let db = test.in_memory_database();
 
         let tasks = db.async_tasks().spawn_with(|f| test.ex.spawn(f));
@@ -53,7 +62,7 @@ impl SyncStreamTest {
 
     async fn wait_for_progress(&self, name: &'static str, completed: i64, total: i64) {
         self.wait_for_status(|status| {
-            let stream = self.db.sync_stream(name, None);
+            let stream = self.db.sync_stream(name, None).unwrap();
             let Some(actual) = status.for_stream(&stream) else {
                 return false;
             };
@@ -96,6 +105,122 @@ fn can_disable_default_stream() {
     });
 }
 
+#[test]
+fn keeps_default_stream_option_on_reconnect() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    sync.connect_options(|o| {
+        o.set_include_default_streams(false);
+        assert!(!o.include_default_streams());
+    });
+
+    sync.run(async {
+        for _ in 0..2 {
+            // Dropping the request closes the response stream, which causes a reconnect.
+            let request = sync.test.http.receive_requests.recv().await.unwrap();
+            let streams = request.request_data.get("streams").unwrap();
+            assert_eq!(
+                streams.get("include_defaults").unwrap().as_bool(),
+                Some(false)
+            );
+        }
+    });
+}
+
+#[test]
+fn includes_client_parameters() {
+    let sync = SyncStreamTest::new();
+    sync.connect_options(|o| {
+        let Value::Object(parameters) = json!({
+            "tenant": "a",
+            "nested": {"roles": ["admin", "editor"], "level": 3},
+        }) else {
+            unreachable!()
+        };
+        o.with_parameters(parameters);
+    });
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(
+            request.request_data.get("parameters").unwrap(),
+            &json!({
+                "tenant": "a",
+                "nested": {"roles": ["admin", "editor"], "level": 3},
+            })
+        );
+    });
+}
+
+#[test]
+fn reconnects_on_changed_options() {
+    let sync = SyncStreamTest::new();
+    let connect_with_tenant = |tenant: &str| {
+        sync.connect_options(|o| {
+            let Value::Object(parameters) = json!({"tenant": tenant}) else {
+                unreachable!()
+            };
+            o.with_parameters(parameters);
+        })
+    };
+
+    connect_with_tenant("a");
+    // Keep the first response open, so that only the changed options cause a reconnect.
+    let _first = sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(
+            request.request_data.get("parameters").unwrap(),
+            &json!({"tenant": "a"})
+        );
+        request
+    });
+
+    connect_with_tenant("b");
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(
+            request.request_data.get("parameters").unwrap(),
+            &json!({"tenant": "b"})
+        );
+    });
+}
+
+#[test]
+fn sends_user_agent() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+    // Keep the first response open, so that only the changed options cause a reconnect.
+    let _first = sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert!(
+            request
+                .header("User-Agent")
+                .unwrap()
+                .starts_with("powersync-native/")
+        );
+        request
+    });
+
+    sync.connect_options(|o| o.with_user_agent("my-app/1.0".to_string()));
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(request.header("User-Agent"), Some("my-app/1.0"));
+    });
+}
+
+#[test]
+fn sends_custom_headers() {
+    let sync = SyncStreamTest::new();
+    sync.connect_options(|o| o.with_header("X-Tenant", "a").unwrap());
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(request.header("X-Tenant"), Some("a"));
+        assert_eq!(request.header("Authorization"), Some("Token token"));
+    });
+}
+
 #[test]
 fn subscribes_with_streams() {
     let sync = SyncStreamTest::new();
@@ -103,14 +228,14 @@ fn subscribes_with_streams() {
         .run(async {
             let a = sync
                 .db
-                .sync_stream("foo", Some(&json!({"foo": "a"})))
+                .sync_stream("foo", Some(&json!({"foo": "a"})))?
                 .subscribe()
                 .await?;
             let b = sync
                 .db
-                .sync_stream("foo", Some(&json!({"foo": "b"})))
+                .sync_stream("foo", Some(&json!({"foo": "b"})))?
                 .subscribe_with(
-                    *StreamSubscriptionOptions::default().with_priority(StreamPriority::ONE),
+                    StreamSubscriptionOptions::default().with_priority(StreamPriority::ONE),
                 )
                 .await?;
             Ok::<(StreamSubscription, StreamSubscription), PowerSyncError>((a, b))
@@ -227,7 +352,13 @@ fn changes_subscriptions_dynamically() {
         let request = sync.test.http.receive_requests.recv().await.unwrap();
         sync.wait_for_status(|s| s.is_connected()).await;
 
-        let subscription = sync.db.sync_stream("a", None).subscribe().await.unwrap();
+        let subscription = sync
+            .db
+            .sync_stream("a", None)
+            .unwrap()
+            .subscribe()
+            .await
+            .unwrap();
 
         // Adding the subscription should reconnect.
         request.channel.closed().await;
@@ -253,6 +384,45 @@ fn changes_subscriptions_dynamically() {
     });
 }
 
+#[test]
+fn subscribe_many_reconnects_once() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.is_connected()).await;
+
+        let streams = ["a", "b", "c"].map(|name| {
+            (
+                sync.db.sync_stream(name, None).unwrap(),
+                StreamSubscriptionOptions::default(),
+            )
+        });
+        let subscriptions = sync.db.subscribe_many(&streams).await.unwrap();
+        assert_eq!(subscriptions.len(), 3);
+
+        request.channel.closed().await;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.is_connected()).await;
+
+        let streams = request
+            .request_data
+            .get("streams")
+            .unwrap()
+            .get("subscriptions")
+            .unwrap();
+        assert_eq!(streams.as_array().unwrap().len(), 3);
+
+        // All subscriptions should have been applied with a single reconnect.
+        assert!(
+            future::poll_once(sync.test.http.receive_requests.recv())
+                .await
+                .is_none()
+        );
+    });
+}
+
 #[test]
 fn subscriptions_update_while_offline() {
     let sync = SyncStreamTest::new();
@@ -266,19 +436,184 @@ fn subscriptions_update_while_offline() {
 
         // Subscribing while offline should add the stream to the subscriptions reported in the
         // status.
-        let subscription = sync.db.sync_stream("foo", None).subscribe().await.unwrap();
+        let subscription = sync
+            .db
+            .sync_stream("foo", None)
+            .unwrap()
+            .subscribe()
+            .await
+            .unwrap();
         let status = next_status.await;
         assert!(status.for_stream(&subscription).is_some());
     });
 }
 
+#[test]
+fn stream_parameters_must_be_object() {
+    let test = DatabaseTest::new();
+    let db = test.in_memory_database();
+
+    let Err(e) = db.sync_stream("a", Some(&json!(42))) else {
+        panic!("Expected number parameters to be rejected");
+    };
+    assert!(e.to_string().contains("JSON object"));
+    assert!(db.sync_stream("a", Some(&json!([1, 2, 3]))).is_err());
+
+    let stream = db.sync_stream("a", None).unwrap();
+    assert!(stream.with_parameters(&json!("foo")).is_err());
+
+    let stream = db.sync_stream("a", None).unwrap();
+    assert!(stream.with_parameters(&json!({"foo": "bar"})).is_ok());
+}
+
+#[test]
+fn prefetches_credentials() {
+    struct NotifyingConnector(async_channel::Sender<()>);
+
+    #[async_trait]
+    impl BackendConnector for NotifyingConnector {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            self.0.send(()).await.unwrap();
+            TestConnector.fetch_credentials().await
+        }
+
+        async fn upload_data(&self) -> Result<(), PowerSyncError> {
+            Ok(())
+        }
+    }
+
+    let sync = SyncStreamTest::new();
+    let (fetched, fetch_calls) = async_channel::unbounded();
+    sync.run(async {
+        sync.db
+            .connect(SyncOptions::new(NotifyingConnector(fetched)))
+            .await;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        fetch_calls.recv().await.unwrap();
+
+        // A token expiring soon makes the core extension request a pre-fetch.
+        request
+            .channel
+            .send(SyncLine::Custom(json!({"token_expires_in": 10})))
+            .await
+            .unwrap();
+        fetch_calls.recv().await.unwrap();
+    });
+}
+
+/// A [TransactionUploader] marking all transactions as uploaded without doing anything.
+struct NoopUploader;
+
+#[async_trait]
+impl TransactionUploader for NoopUploader {
+    async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+        TestConnector.fetch_credentials().await
+    }
+
+    async fn upload_transaction(
+        &self,
+        _transaction: &CrudTransaction<'_>,
+    ) -> Result<(), PowerSyncError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn wait_for_uploads_complete() {
+    let sync = SyncStreamTest::new();
+    sync.run(async {
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["local"],
+        )
+        .await;
+
+        let connector = TransactionConnector::new(sync.db.clone(), NoopUploader);
+        sync.db.connect(SyncOptions::new(connector)).await;
+        sync.test.http.receive_requests.recv().await.unwrap();
+
+        sync.db.wait_for_uploads_complete().await.unwrap();
+        assert!(sync.db.next_crud_transaction().await.unwrap().is_none());
+        assert!(!sync.db.status().is_uploading());
+    });
+}
+
+#[test]
+fn has_pending_uploads() {
+    let sync = SyncStreamTest::new();
+    sync.run(async {
+        sync.wait_for_status(|s| !s.has_pending_uploads()).await;
+
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["local"],
+        )
+        .await;
+        sync.wait_for_status(|s| s.has_pending_uploads()).await;
+        assert!(!sync.db.status().is_uploading());
+
+        let connector = TransactionConnector::new(sync.db.clone(), NoopUploader);
+        sync.db.connect(SyncOptions::new(connector)).await;
+        sync.wait_for_status(|s| !s.has_pending_uploads()).await;
+    });
+}
+
+#[test]
+fn reports_stuck_crud_item() {
+    struct ForgetfulConnector(async_channel::Sender<i64>);
+
+    #[async_trait]
+    impl BackendConnector for ForgetfulConnector {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            TestConnector.fetch_credentials().await
+        }
+
+        async fn upload_data(&self) -> Result<(), PowerSyncError> {
+            // Never completes transactions.
+            Ok(())
+        }
+
+        fn on_stuck_crud_item(&self, entry: &CrudEntry) {
+            let _ = self.0.try_send(entry.client_id);
+        }
+    }
+
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    let (send, stuck_items) = async_channel::unbounded();
+
+    sync.run(async {
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["local"],
+        )
+        .await;
+
+        sync.db
+            .connect(SyncOptions::new(ForgetfulConnector(send)))
+            .await;
+        assert_eq!(stuck_items.recv().await.unwrap(), 1);
+    });
+}
+
 #[test]
 fn unsubscribe_all() {
     let sync = SyncStreamTest::new();
     sync.run(async {
-        let a = sync.db.sync_stream("a", None).subscribe().await.unwrap();
+        let a = sync
+            .db
+            .sync_stream("a", None)
+            .unwrap()
+            .subscribe()
+            .await
+            .unwrap();
         sync.db
             .sync_stream("a", None)
+            .unwrap()
             .unsubscribe_all()
             .await
             .unwrap();
@@ -299,6 +634,69 @@ fn unsubscribe_all() {
     });
 }
 
+#[test]
+fn unsubscribe_now() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.is_connected()).await;
+
+        let a = sync
+            .db
+            .sync_stream("a", None)
+            .unwrap()
+            .subscribe_with(StreamSubscriptionOptions::default().with_ttl(Duration::ZERO))
+            .await
+            .unwrap();
+
+        request.channel.closed().await;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.is_connected()).await;
+        let streams = request
+            .request_data
+            .get("streams")
+            .unwrap()
+            .get("subscriptions")
+            .unwrap();
+        assert_eq!(streams.as_array().unwrap().len(), 1);
+
+        // Without a TTL, unsubscribing while connected should reconnect without the stream.
+        a.unsubscribe_now().await.unwrap();
+        request.channel.closed().await;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        let streams = request
+            .request_data
+            .get("streams")
+            .unwrap()
+            .get("subscriptions")
+            .unwrap();
+
+        assert_eq!(streams, &json!([]));
+    });
+}
+
+#[test]
+fn unsubscribe_now_after_close() {
+    let sync = SyncStreamTest::new();
+    sync.run(async {
+        let a = sync
+            .db
+            .sync_stream("a", None)
+            .unwrap()
+            .subscribe()
+            .await
+            .unwrap();
+        sync.db.close().await;
+
+        let Err(e) = a.unsubscribe_now().await else {
+            panic!("Expected unsubscribe_now to fail after close()");
+        };
+        assert_eq!(e.to_string(), "The database has been closed");
+    });
+}
+
 #[test]
 fn progress_without_priorities() {
     let sync = SyncStreamTest::new();
@@ -333,3 +731,1114 @@ fn progress_without_priorities() {
         sync.wait_for_status(|s| !s.is_downloading()).await;
     });
 }
+
+#[test]
+fn connection_state_ignores_progress() {
+    let sync = SyncStreamTest::new();
+    let mut states = sync.db.watch_connection_state();
+    assert_eq!(
+        sync.run(states.next()).unwrap(),
+        ConnectionState::Disconnected
+    );
+    sync.connect();
+
+    sync.run(async {
+        let mut oplog_id = 0;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        loop {
+            if states.next().await.unwrap() == ConnectionState::Connected {
+                break;
+            }
+        }
+
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 10, None))
+            .await;
+        request.bogus_data_line(&mut oplog_id, "a", 5).await;
+        sync.wait_for_progress("a", 5, 10).await;
+
+        // Progress updates don't change the connection state.
+        assert!(future::poll_once(states.next()).await.is_none());
+    });
+}
+
+#[test]
+fn prefers_bson_by_default() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert!(
+            request
+                .header("Accept")
+                .unwrap()
+                .starts_with("application/vnd.powersync.bson-stream")
+        );
+    });
+}
+
+#[test]
+fn bson_and_ndjson_responses_are_equivalent() {
+    use powersync_test_utils::{
+        query_all,
+        sync_line::{DataLine, OpType, OplogEntry},
+    };
+
+    fn synced_users(bson: bool) -> Value {
+        let sync = SyncStreamTest::new();
+        *sync.test.http.sync_stream_bson.lock().unwrap() = bson;
+        sync.connect();
+
+        sync.run(async {
+            let request = sync.test.http.receive_requests.recv().await.unwrap();
+            request
+                .send_checkpoint(Checkpoint::single_bucket("a", 1, None))
+                .await;
+            request
+                .channel
+                .send(SyncLine::Data(DataLine {
+                    bucket: "a",
+                    data: vec![OplogEntry {
+                        checksum: 0,
+                        op_id: 1,
+                        op: OpType::PUT,
+                        object_id: Some("1".to_string()),
+                        object_type: Some("users"),
+                        subkey: None,
+                        data: Some(r#"{"name":"steven","email":"steven@journeyapps.com"}"#),
+                    }],
+                }))
+                .await
+                .unwrap();
+            request.send_checkpoint_complete(1, None).await;
+            sync.wait_for_status(|s| s.has_synced()).await;
+
+            query_all(&sync.db, "SELECT id, name, email FROM users", params![]).await
+        })
+    }
+
+    let ndjson = synced_users(false);
+    assert_eq!(
+        ndjson,
+        json!([{"id": "1", "name": "steven", "email": "steven@journeyapps.com"}])
+    );
+    assert_eq!(synced_users(true), ndjson);
+}
+
+#[test]
+fn can_prefer_ndjson() {
+    let sync = SyncStreamTest::new();
+    sync.connect_options(|options| options.prefer_ndjson(true));
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert!(
+            request
+                .header("Accept")
+                .unwrap()
+                .starts_with("application/x-ndjson")
+        );
+    });
+}
+
+#[test]
+fn retries_after_connect_timeout() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    sync.connect_options(|options| options.with_connect_timeout(Duration::from_secs(10)));
+
+    sync.run(async {
+        // The first request never receives a line, so the client should give up and reconnect.
+        let _stalled = sync.test.http.receive_requests.recv().await.unwrap();
+        let _retry = sync.test.http.receive_requests.recv().await.unwrap();
+
+        let status = sync.db.status();
+        assert!(status.download_error().is_some());
+    });
+}
+
+#[test]
+fn retries_after_idle_timeout() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    sync.connect_options(|options| options.with_idle_timeout(Duration::from_secs(10)));
+
+    sync.run(async {
+        // The idle timeout only starts after the first line.
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 1, None))
+            .await;
+
+        // After going quiet, the client should reconnect.
+        let _retry = sync.test.http.receive_requests.recv().await.unwrap();
+        let status = sync.db.status();
+        assert!(status.download_error().is_some());
+    });
+}
+
+#[test]
+fn notifies_observer() {
+    use powersync::SyncObserver;
+    use std::sync::Arc;
+
+    struct RecordingObserver {
+        events: async_channel::Sender<&'static str>,
+    }
+
+    impl SyncObserver for RecordingObserver {
+        fn on_iteration_start(&self) {
+            let _ = self.events.try_send("iteration_start");
+        }
+
+        fn on_line_received(&self, bytes: usize) {
+            assert!(bytes > 0);
+            let _ = self.events.try_send("line");
+        }
+
+        fn on_checkpoint_complete(&self) {
+            let _ = self.events.try_send("checkpoint_complete");
+        }
+    }
+
+    let (send_events, events) = async_channel::unbounded();
+    let sync = SyncStreamTest::new();
+    sync.connect_options(|options| {
+        options.with_observer(Arc::new(RecordingObserver {
+            events: send_events,
+        }))
+    });
+
+    sync.run(async {
+        let mut oplog_id = 0;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), "iteration_start");
+
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 1, None))
+            .await;
+        request.bogus_data_line(&mut oplog_id, "a", 1).await;
+        request.send_checkpoint_complete(oplog_id, None).await;
+
+        for _ in 0..3 {
+            assert_eq!(events.recv().await.unwrap(), "line");
+        }
+        assert_eq!(events.recv().await.unwrap(), "checkpoint_complete");
+    });
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn emits_tracing_spans() {
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    };
+    use tracing::{Event, Metadata, Subscriber, span};
+
+    struct RecordingSubscriber {
+        spans: Arc<Mutex<Vec<&'static str>>>,
+        next_id: AtomicU64,
+    }
+
+    impl Subscriber for RecordingSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            self.spans.lock().unwrap().push(span.metadata().name());
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst))
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    let spans = Arc::new(Mutex::new(vec![]));
+    let subscriber = RecordingSubscriber {
+        spans: spans.clone(),
+        next_id: AtomicU64::new(1),
+    };
+
+    tracing::subscriber::with_default(subscriber, || {
+        let sync = SyncStreamTest::new();
+        sync.connect();
+
+        sync.run(async {
+            let _request = sync.test.http.receive_requests.recv().await.unwrap();
+            sync.wait_for_status(|s| s.is_connected()).await;
+        });
+    });
+
+    let spans = spans.lock().unwrap();
+    assert!(spans.contains(&"sync_iteration"));
+    assert!(spans.contains(&"establish_sync_stream"));
+}
+
+#[test]
+fn connect_and_wait() {
+    let sync = SyncStreamTest::new();
+    sync.run(async {
+        let mock_service = async {
+            let request = sync.test.http.receive_requests.recv().await.unwrap();
+            request
+                .send_checkpoint(Checkpoint::single_bucket("a", 0, None))
+                .await;
+            request.send_checkpoint_complete(0, None).await;
+        };
+
+        let (result, _) = future::zip(
+            sync.db
+                .connect_and_wait(SyncOptions::new(TestConnector), None),
+            mock_service,
+        )
+        .await;
+        result.unwrap();
+        assert!(sync.db.status().has_synced());
+        assert!(
+            sync.db
+                .status()
+                .has_synced_priority(StreamPriority::HIGHEST)
+        );
+    });
+}
+
+#[test]
+fn disconnect_resolves_offline_status() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 10, None))
+            .await;
+        sync.wait_for_status(|s| s.is_downloading()).await;
+
+        sync.db.disconnect().await;
+        let status = sync.db.status();
+        assert!(!status.is_downloading());
+        assert!(!status.is_connected());
+        assert_eq!(status.connection_state(), ConnectionState::Disconnected);
+    });
+}
+
+#[test]
+fn same_connector_does_not_restart_upload() {
+    use powersync::SyncObserver;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    struct GatedUploader {
+        started: async_channel::Sender<()>,
+        release: async_channel::Receiver<()>,
+    }
+
+    #[async_trait]
+    impl TransactionUploader for GatedUploader {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            TestConnector.fetch_credentials().await
+        }
+
+        async fn upload_transaction(
+            &self,
+            _transaction: &CrudTransaction<'_>,
+        ) -> Result<(), PowerSyncError> {
+            self.started.send(()).await.unwrap();
+            self.release.recv().await.unwrap();
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct UploadObserver {
+        uploads: AtomicUsize,
+    }
+
+    impl SyncObserver for UploadObserver {
+        fn on_upload_complete(&self, _batches: usize) {
+            self.uploads.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let sync = SyncStreamTest::new();
+    let (started, upload_started) = async_channel::unbounded();
+    let (release_upload, release) = async_channel::unbounded();
+
+    sync.run(async {
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["local"],
+        )
+        .await;
+
+        let connector =
+            TransactionConnector::new(sync.db.clone(), GatedUploader { started, release });
+        let options = SyncOptions::new(connector);
+        sync.db.connect(options.clone()).await;
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        upload_started.recv().await.unwrap();
+
+        // Connecting again with the same connector shouldn't abort the upload.
+        let observer = Arc::new(UploadObserver::default());
+        let mut updated = options.clone();
+        updated.with_observer(observer.clone());
+        sync.db.connect(updated).await;
+        release_upload.send(()).await.unwrap();
+        sync.db.wait_for_uploads_complete().await.unwrap();
+        assert!(upload_started.try_recv().is_err());
+        // The running upload was started with the old options, without an observer.
+        assert_eq!(observer.uploads.load(Ordering::SeqCst), 0);
+
+        // Subsequent uploads use the options from the second connect() call.
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["another"],
+        )
+        .await;
+        upload_started.recv().await.unwrap();
+        release_upload.send(()).await.unwrap();
+        sync.db.wait_for_uploads_complete().await.unwrap();
+        assert_eq!(observer.uploads.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn stops_retrying_uploads_after_max_attempts() {
+    use powersync::RetryPolicy;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    struct FailingUploader {
+        attempts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl TransactionUploader for FailingUploader {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            TestConnector.fetch_credentials().await
+        }
+
+        async fn upload_transaction(
+            &self,
+            _transaction: &CrudTransaction<'_>,
+        ) -> Result<(), PowerSyncError> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            Err(PowerSyncError::argument_error("rejected by backend"))
+        }
+    }
+
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    sync.run(async {
+        execute(
+            &sync.db,
+            "INSERT INTO users (id, name) VALUES (uuid(), ?)",
+            params!["local"],
+        )
+        .await;
+
+        let connector = TransactionConnector::new(
+            sync.db.clone(),
+            FailingUploader {
+                attempts: attempts.clone(),
+            },
+        );
+        let mut options = SyncOptions::new(connector);
+        options.with_retry_policy(
+            RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(10))
+                .with_max_attempts(3),
+        );
+        sync.db.connect(options).await;
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+
+        sync.wait_for_status(|status| status.fatal_error().is_some())
+            .await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    });
+}
+
+#[test]
+fn watch_priority_completions() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let mut completions = sync.db.watch_priority_completions();
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+
+        let (stream_a, bucket_a) = BucketChecksum::with_stream("a", 0, Some(StreamPriority::ONE));
+        let (stream_b, mut bucket_b) =
+            BucketChecksum::with_stream("b", 0, Some(StreamPriority::THREE));
+        bucket_b.subscriptions = vec![BucketSubscriptionReason::DerivedFromDefaultStream(1)];
+        request
+            .send_checkpoint(Checkpoint {
+                last_op_id: 0,
+                write_checkpoint: None,
+                buckets: vec![bucket_a, bucket_b],
+                streams: vec![stream_a, stream_b],
+            })
+            .await;
+
+        request
+            .send_checkpoint_complete(0, Some(StreamPriority::ONE))
+            .await;
+        assert_eq!(completions.next().await, Some(StreamPriority::ONE));
+
+        request
+            .send_checkpoint_complete(0, Some(StreamPriority::THREE))
+            .await;
+        assert_eq!(completions.next().await, Some(StreamPriority::THREE));
+    });
+}
+
+#[test]
+fn wait_for_first_complete_sync() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        let mut last_op_id = 0;
+
+        let completed = async {
+            request
+                .send_checkpoint(Checkpoint::single_bucket("a", 5, None))
+                .await;
+            request.bogus_data_line(&mut last_op_id, "a", 5).await;
+            request.send_checkpoint_complete(last_op_id, None).await;
+        };
+        future::zip(sync.db.wait_for_first_complete_sync(), completed).await;
+
+        // Later calls resolve immediately until the next connect() call.
+        sync.db.wait_for_first_complete_sync().await;
+    });
+}
+
+#[test]
+fn raw_download_status() {
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 10, None))
+            .await;
+        sync.wait_for_status(|s| s.is_downloading()).await;
+
+        let status = sync.db.status();
+        let raw = status.raw_download_status();
+        assert_eq!(raw.connected, status.is_connected());
+        assert_eq!(raw.connecting, status.is_connecting());
+        assert_eq!(raw.downloading.is_some(), status.is_downloading());
+        assert_eq!(raw.streams.len(), status.streams().count());
+        let stream: &ActiveStreamSubscription = &raw.streams[0];
+        assert_eq!(stream.name, "a");
+
+        let progress = raw.downloading.as_ref().unwrap().overall();
+        assert_eq!((progress.downloaded, progress.total), (0, 10));
+    });
+}
+
+#[test]
+fn refresh_offline_status() {
+    let test = DatabaseTest::new();
+    let db = test.test_dir_database();
+    let _tasks = db.async_tasks().spawn_with(|f| test.ex.spawn(f));
+
+    future::block_on(test.ex.run(async {
+        db.connect(SyncOptions::new(TestConnector)).await;
+        let request = test.http.receive_requests.recv().await.unwrap();
+        request
+            .send_checkpoint(Checkpoint::single_bucket("a", 0, None))
+            .await;
+        request.send_checkpoint_complete(0, None).await;
+        db.wait_for_first_complete_sync().await;
+        db.disconnect().await;
+    }));
+
+    // A new database opened on the same file should report the previous sync.
+    let db = test.test_dir_database();
+    future::block_on(async {
+        assert!(!db.status().has_synced());
+        db.refresh_offline_status().await.unwrap();
+        assert!(db.status().has_synced());
+    });
+}
+
+#[test]
+fn close_stops_actors() {
+    let mut sync = SyncStreamTest::new();
+    let tasks = std::mem::take(&mut sync.tasks);
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.is_connected()).await;
+
+        sync.db.close().await;
+        for task in tasks {
+            task.await;
+        }
+
+        assert_eq!(
+            sync.db.status().connection_state(),
+            ConnectionState::Disconnected
+        );
+        // Requests to stopped actors are ignored.
+        sync.db.disconnect().await;
+        // Closing the database also closes its connections.
+        assert!(sync.db.reader().await.is_err());
+    });
+}
+
+#[test]
+fn uses_rotated_endpoint_on_reconnect() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct RotatingConnector(AtomicUsize);
+
+    #[async_trait]
+    impl BackendConnector for RotatingConnector {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            let region = match self.0.fetch_add(1, Ordering::SeqCst) % 2 {
+                0 => "eu",
+                _ => "us",
+            };
+
+            Ok(PowerSyncCredentials {
+                endpoint: format!("https://{region}.powersync.example.com/"),
+                token: "token".to_string(),
+                expires_at: None,
+            })
+        }
+
+        async fn upload_data(&self) -> Result<(), PowerSyncError> {
+            Ok(())
+        }
+    }
+
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    sync.run(
+        sync.db
+            .connect(SyncOptions::new(RotatingConnector(AtomicUsize::new(0)))),
+    );
+
+    sync.run(async {
+        // Credentials without an expiry date are fetched again for each iteration, so dropping
+        // the first response should make the client reconnect to the other endpoint.
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(request.url, "https://eu.powersync.example.com/sync/stream");
+        drop(request);
+
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(request.url, "https://us.powersync.example.com/sync/stream");
+    });
+}
+
+#[test]
+fn sends_unique_request_ids() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    sync.connect();
+
+    sync.run(async {
+        let mut ids = vec![];
+        for _ in 0..2 {
+            // Dropping the request closes the response stream, which causes a reconnect.
+            let request = sync.test.http.receive_requests.recv().await.unwrap();
+            ids.push(request.header("X-Request-Id").unwrap().to_string());
+        }
+
+        assert_eq!(ids[0].len(), 36);
+        assert_ne!(ids[0], ids[1]);
+        for id in &ids {
+            // Request ids are formatted as version 4 UUIDs.
+            let groups: Vec<_> = id.split('-').map(str::len).collect();
+            assert_eq!(groups, [8, 4, 4, 4, 12]);
+            assert_eq!(id.as_bytes()[14], b'4');
+            assert!(matches!(id.as_bytes()[19], b'8' | b'9' | b'a' | b'b'));
+        }
+    });
+}
+
+#[test]
+fn request_write_checkpoint() {
+    let sync = SyncStreamTest::new();
+    sync.run(async {
+        assert!(sync.db.request_write_checkpoint().await.is_err());
+    });
+
+    *sync.test.http.write_checkpoints.lock().unwrap() =
+        Box::new(|| WriteCheckpointResponse::new("42".to_string()));
+    sync.connect();
+    sync.run(async {
+        assert_eq!(sync.db.request_write_checkpoint().await.unwrap(), 42);
+    });
+}
+
+#[test]
+fn cancel_wait_for_first_sync() {
+    let sync = SyncStreamTest::new();
+    let subscription = sync
+        .run(async { sync.db.sync_stream("foo", None)?.subscribe().await })
+        .unwrap();
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        let token = CancellationToken::new();
+
+        let (result, _) = future::zip(token.run(subscription.wait_for_first_sync()), async {
+            future::yield_now().await;
+            token.cancel();
+        })
+        .await;
+
+        assert_eq!(result.unwrap_err().kind(), PowerSyncErrorKind::Cancelled);
+    });
+}
+
+#[test]
+fn status_change_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let sync = SyncStreamTest::new();
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let listener = sync.db.on_status_changed({
+        let states = states.clone();
+        move |status| states.lock().unwrap().push(status.connection_state())
+    });
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.connection_state() == ConnectionState::Connected)
+            .await;
+
+        let recorded = states.lock().unwrap().clone();
+        assert!(recorded.contains(&ConnectionState::Connecting));
+        assert_eq!(recorded.last(), Some(&ConnectionState::Connected));
+
+        drop(listener);
+        let count = states.lock().unwrap().len();
+        sync.db.disconnect().await;
+        assert_eq!(states.lock().unwrap().len(), count);
+    });
+}
+
+#[test]
+fn watch_status_all_emits_every_revision() {
+    let sync = SyncStreamTest::new();
+    let mut statuses = sync.db.watch_status_all(16);
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.connection_state() == ConnectionState::Connected)
+            .await;
+
+        // watch_status only reports the latest state, but this stream includes all transitions.
+        let mut states = vec![];
+        while let Some(Some(status)) = future::poll_once(statuses.next()).await {
+            states.push(status.connection_state());
+        }
+        states.dedup();
+        assert!(states.ends_with(&[ConnectionState::Connecting, ConnectionState::Connected]));
+    });
+}
+
+#[test]
+fn watch_status_all_with_zero_capacity() {
+    let sync = SyncStreamTest::new();
+    let mut statuses = sync.db.watch_status_all(0);
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        // The stream buffers at least one revision.
+        statuses.next().await.unwrap();
+    });
+}
+
+#[test]
+fn watch_status_all_keeps_latest_revision() {
+    let sync = SyncStreamTest::new();
+    let mut statuses = sync.db.watch_status_all(1);
+    sync.connect();
+
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        sync.wait_for_status(|s| s.connection_state() == ConnectionState::Connected)
+            .await;
+
+        // Without polling the stream, older revisions are dropped in favor of the latest one.
+        let status = statuses.next().await.unwrap();
+        assert_eq!(status.connection_state(), ConnectionState::Connected);
+    });
+}
+
+#[test]
+fn watch_tables_emits_for_synced_data() {
+    let sync = SyncStreamTest::new();
+    let mut updates = sync.db.watch_tables(false, ["users"]);
+    sync.connect();
+
+    sync.run(async {
+        let mut oplog_id = 0;
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        request
+            .send_checkpoint(Checkpoint::single_bucket("users", 2, None))
+            .await;
+        request.bogus_data_line(&mut oplog_id, "users", 2).await;
+        assert!(future::poll_once(updates.next()).await.is_none());
+
+        request.send_checkpoint_complete(oplog_id, None).await;
+        updates.next().await.unwrap();
+
+        // Synced rows are visible once the stream emits.
+        let count = sync
+            .db
+            .query_one("SELECT COUNT(*) FROM users", params![], |row| {
+                row.get::<_, i64>(0)
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    });
+}
+
+#[test]
+fn watch_list_emits_on_data_and_status_changes() {
+    let sync = SyncStreamTest::new();
+    let subscription = sync
+        .run(async { sync.db.sync_stream("foo", None)?.subscribe().await })
+        .unwrap();
+    let mut list = sync.db.watch_list(
+        ["users"],
+        &subscription,
+        "SELECT name FROM users".to_string(),
+        params![],
+        |row| row.get::<_, String>(0),
+    );
+
+    sync.run(async {
+        let (rows, _) = list.next().await.unwrap().unwrap();
+        assert!(rows.is_empty());
+
+        // Writes to the table trigger a new emission.
+        sync.db
+            .execute(
+                "INSERT INTO users (id, name, email) VALUES (uuid(), ?, ?)",
+                params!["Test user", "test@example.org"],
+            )
+            .await
+            .unwrap();
+        let (rows, _) = list.next().await.unwrap().unwrap();
+        assert_eq!(rows, vec!["Test user".to_string()]);
+    });
+
+    sync.connect();
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        request
+            .channel
+            .send(SyncLine::Custom(json!({"checkpoint": {
+                "last_op_id": "0",
+                "streams": [
+                    {"name": "foo", "is_default": false, "errors": []}
+                ],
+                "buckets": [
+                    {"bucket": "a", "priority": 3, "checksum": 0, "subscriptions": [
+                        {"sub": 0}
+                    ]}
+                ],
+            }})))
+            .await
+            .unwrap();
+
+        // So do changes to the status of the stream.
+        loop {
+            let (rows, status) = list.next().await.unwrap().unwrap();
+            assert_eq!(rows, vec!["Test user".to_string()]);
+
+            if status
+                .for_stream(&subscription)
+                .is_some_and(|stream| stream.subscription.is_active())
+            {
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn reports_sync_request_to_observer() {
+    use powersync::SyncObserver;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RequestObserver {
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl SyncObserver for RequestObserver {
+        fn on_sync_request(&self, request: &str) {
+            self.requests.lock().unwrap().push(request.to_string());
+        }
+    }
+
+    let observer = Arc::new(RequestObserver::default());
+    let sync = SyncStreamTest::new();
+    sync.connect_options(|options| options.with_observer(observer.clone()));
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        let reported = observer.requests.lock().unwrap().clone();
+        assert_eq!(reported.len(), 1);
+
+        let reported: Value = serde_json::from_str(&reported[0]).unwrap();
+        assert_eq!(reported, request.request_data);
+    });
+}
+
+#[test]
+fn test_connection() {
+    let sync = SyncStreamTest::new();
+    let options = SyncOptions::new(TestConnector);
+
+    let tested_request = sync.run(async {
+        let (result, request_data) = future::zip(sync.db.test_connection(&options), async {
+            let request = sync.test.http.receive_requests.recv().await.unwrap();
+            request
+                .send_checkpoint(Checkpoint::single_bucket("a", 0, None))
+                .await;
+            request.request_data
+        })
+        .await;
+
+        result.unwrap();
+        assert_eq!(
+            sync.db.status().connection_state(),
+            ConnectionState::Disconnected
+        );
+        request_data
+    });
+
+    *sync.test.http.sync_stream_status.lock().unwrap() = 401;
+    sync.run(async {
+        let err = sync.db.test_connection(&options).await.unwrap_err();
+        assert_eq!(err.kind(), PowerSyncErrorKind::InvalidCredentials);
+    });
+
+    // The tested request is the one sent when connecting with the same options.
+    *sync.test.http.sync_stream_status.lock().unwrap() = 200;
+    sync.run(sync.db.connect(options.clone()));
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert_eq!(request.request_data, tested_request);
+
+        let err = sync.db.test_connection(&options).await.unwrap_err();
+        assert_eq!(err.kind(), PowerSyncErrorKind::InvalidArgument);
+    });
+}
+
+#[test]
+fn unexpected_status_code_includes_body() {
+    let sync = SyncStreamTest::new();
+    *sync.test.http.sync_stream_status.lock().unwrap() = 500;
+    *sync.test.http.sync_stream_error_body.lock().unwrap() =
+        r#"{"error":{"code":"PSYNC_S2001","description":"Replication lag"}}"#.to_string();
+
+    sync.run(async {
+        let err = sync
+            .db
+            .test_connection(&SyncOptions::new(TestConnector))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), PowerSyncErrorKind::Network);
+        let message = err.to_string();
+        assert!(message.contains("500"), "{message}");
+        assert!(message.contains("PSYNC_S2001"), "{message}");
+    });
+}
+
+#[test]
+fn unexpected_status_code_with_stalled_body() {
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    *sync.test.http.sync_stream_status.lock().unwrap() = 503;
+    *sync.test.http.sync_stream_error_body.lock().unwrap() = "Service unavailable".to_string();
+    *sync.test.http.stall_sync_stream_error_body.lock().unwrap() = true;
+
+    sync.run(async {
+        // The body never ends, so the error is reported with the part received before the timeout.
+        let err = sync
+            .db
+            .test_connection(&SyncOptions::new(TestConnector))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), PowerSyncErrorKind::Network);
+        let message = err.to_string();
+        assert!(message.contains("503"), "{message}");
+        assert!(message.contains("Service unavailable"), "{message}");
+    });
+}
+
+#[test]
+fn stops_retrying_after_max_attempts() {
+    use powersync::{RetryPolicy, SyncObserver};
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Default)]
+    struct CountingObserver {
+        iterations: AtomicUsize,
+    }
+
+    impl SyncObserver for CountingObserver {
+        fn on_iteration_start(&self) {
+            self.iterations.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut test = DatabaseTest::new();
+    test.timer = &ImmediateTimer;
+    let sync = SyncStreamTest::with_test(test);
+    *sync.test.http.sync_stream_status.lock().unwrap() = 500;
+
+    let observer = Arc::new(CountingObserver::default());
+    sync.connect_options(|options| {
+        options.with_observer(observer.clone());
+        options.with_retry_policy(
+            RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(10))
+                .with_max_attempts(3),
+        );
+    });
+
+    sync.run(async {
+        sync.wait_for_status(|status| status.fatal_error().is_some())
+            .await;
+        assert_eq!(observer.iterations.load(Ordering::SeqCst), 3);
+
+        let status = sync.db.status();
+        assert_eq!(status.connection_state(), ConnectionState::Error);
+        assert_eq!(
+            status.fatal_error().unwrap().kind(),
+            PowerSyncErrorKind::Network
+        );
+    });
+
+    // Connecting again resets the fatal error.
+    *sync.test.http.sync_stream_status.lock().unwrap() = 200;
+    sync.connect();
+    sync.run(async {
+        let _request = sync.test.http.receive_requests.recv().await.unwrap();
+        assert!(sync.db.status().fatal_error().is_none());
+    });
+}
+
+#[test]
+fn refetches_rejected_credentials() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use std::time::SystemTime;
+
+    struct ExpiringConnector(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl BackendConnector for ExpiringConnector {
+        async fn fetch_credentials(&self) -> Result<PowerSyncCredentials, PowerSyncError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(PowerSyncCredentials {
+                expires_at: Some(SystemTime::now() + Duration::from_secs(3600)),
+                ..TestConnector.fetch_credentials().await?
+            })
+        }
+
+        async fn upload_data(&self) -> Result<(), PowerSyncError> {
+            Ok(())
+        }
+    }
+
+    let sync = SyncStreamTest::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let options = SyncOptions::new(ExpiringConnector(calls.clone()));
+    *sync.test.http.sync_stream_status.lock().unwrap() = 401;
+
+    sync.run(async {
+        for expected_calls in 1..=2 {
+            let err = sync.db.test_connection(&options).await.unwrap_err();
+            assert_eq!(err.kind(), PowerSyncErrorKind::InvalidCredentials);
+            // Although the token hasn't expired, it must not be reused after being rejected.
+            assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+        }
+    });
+}
+
+#[test]
+fn reports_stream_errors() {
+    use powersync_test_utils::sync_line::{StreamSubscriptionError, StreamSubscriptionErrorCause};
+
+    let sync = SyncStreamTest::new();
+    sync.connect();
+
+    sync.run(async {
+        let request = sync.test.http.receive_requests.recv().await.unwrap();
+        let mut checkpoint = Checkpoint::single_bucket("a", 0, None);
+        checkpoint.streams[0].errors.push(StreamSubscriptionError {
+            subscription: StreamSubscriptionErrorCause::Default,
+            message: "invalid parameters",
+        });
+        request.send_checkpoint(checkpoint).await;
+
+        let stream = sync.db.sync_stream("a", None).unwrap();
+        sync.wait_for_status(|status| {
+            status
+                .for_stream(&stream)
+                .is_some_and(|stream| !stream.subscription.errors().is_empty())
+        })
+        .await;
+
+        let status = sync.db.status();
+        let errors = status
+            .for_stream(&stream)
+            .unwrap()
+            .subscription
+            .errors()
+            .to_vec();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "invalid parameters");
+    });
+}
@@ -20,6 +20,8 @@ pub struct DatabaseTest {
     pub dir: TempDir,
     pub http: Arc<MockSyncService>,
     pub ex: Executor<'static>,
+    /// The timer used by databases opened in this test, which panics on delays by default.
+    pub timer: &'static (dyn Timer + Send + Sync),
 }
 
 impl Default for DatabaseTest {
@@ -33,6 +35,7 @@ impl Default for DatabaseTest {
             dir: TempDir::new("powersync_rust").expect("should create test directory"),
             http: Arc::new(MockSyncService::new()),
             ex: Executor::new(),
+            timer: &DisabledTimer,
         }
     }
 }
@@ -67,19 +70,7 @@ impl DatabaseTest {
 
     fn env(&self, pool: ConnectionPool) -> PowerSyncEnvironment {
         PowerSyncEnvironment::powersync_auto_extension().expect("should load core extension");
-
-        struct DisabledTimer;
-
-        impl Timer for DisabledTimer {
-            fn delay_once(
-                &self,
-                _duration: std::time::Duration,
-            ) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send>> {
-                panic!("Tests should not run into a delay")
-            }
-        }
-
-        PowerSyncEnvironment::custom(self.http.clone().client(), pool, &DisabledTimer)
+        PowerSyncEnvironment::custom(self.http.clone().client(), pool, self.timer)
     }
 
     pub fn default_schema() -> Schema {
@@ -90,6 +81,30 @@ impl DatabaseTest {
     }
 }
 
+struct DisabledTimer;
+
+impl Timer for DisabledTimer {
+    fn delay_once(
+        &self,
+        _duration: std::time::Duration,
+    ) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send>> {
+        panic!("Tests should not run into a delay")
+    }
+}
+
+/// A [Timer] completing all delays right away, for tests that need to run into retries or
+/// timeouts.
+pub struct ImmediateTimer;
+
+impl Timer for ImmediateTimer {
+    fn delay_once(
+        &self,
+        _duration: std::time::Duration,
+    ) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(futures_lite::future::yield_now())
+    }
+}
+
 /// Runs a query and returns rows as a `serde_json` array.
 pub async fn query_all(db: &PowerSyncDatabase, sql: &str, params: impl Params) -> Value {
     let reader = db.reader().await.unwrap();
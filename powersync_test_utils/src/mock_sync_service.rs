@@ -6,7 +6,7 @@ use pin_project_lite::pin_project;
 use powersync::http::{HttpClient, Request, Response, ResponseBody};
 use powersync::{BackendConnector, PowerSyncCredentials, StreamPriority, error::PowerSyncError};
 use serde::Serialize;
-use serde_json::json;
+use serde_json::{Map, Value, json};
 use std::pin::Pin;
 use std::task::Context;
 use std::{
@@ -18,6 +18,16 @@ pub struct MockSyncService {
     pub receive_requests: async_channel::Receiver<PendingSyncResponse>,
     send_requests: async_channel::Sender<PendingSyncResponse>,
     pub write_checkpoints: Mutex<Box<dyn Fn() -> WriteCheckpointResponse + Send>>,
+    /// The status code to respond with for sync requests. Requests are only forwarded to
+    /// [Self::receive_requests] if this is `200`.
+    pub sync_stream_status: Mutex<u16>,
+    /// The response body sent along with a [Self::sync_stream_status] other than `200`.
+    pub sync_stream_error_body: Mutex<String>,
+    /// Whether the [Self::sync_stream_error_body] response stalls instead of ending after the body
+    /// has been sent.
+    pub stall_sync_stream_error_body: Mutex<bool>,
+    /// Whether sync lines are encoded as a BSON stream instead of newline-delimited JSON.
+    pub sync_stream_bson: Mutex<bool>,
 }
 
 impl Default for MockSyncService {
@@ -30,6 +40,10 @@ impl Default for MockSyncService {
             write_checkpoints: Mutex::new(Box::new(|| {
                 WriteCheckpointResponse::new("10".to_string())
             })),
+            sync_stream_status: Mutex::new(200),
+            sync_stream_error_body: Mutex::new(String::new()),
+            stall_sync_stream_error_body: Mutex::new(false),
+            sync_stream_bson: Mutex::new(false),
         }
     }
 }
@@ -67,21 +81,63 @@ impl MockSyncService {
     }
 
     async fn sync_stream(&self, req: Request) -> Response {
+        let status = *self.sync_stream_status.lock().unwrap();
+        if status != 200 {
+            let body = Bytes::from(self.sync_stream_error_body.lock().unwrap().clone());
+            let length = body.len() as u64;
+            let reader = stream::once(Ok(body));
+            return Response {
+                status,
+                content_type: None,
+                body: if *self.stall_sync_stream_error_body.lock().unwrap() {
+                    ResponseBody {
+                        length: None,
+                        reader: reader.chain(stream::pending()).boxed(),
+                    }
+                } else {
+                    ResponseBody {
+                        length: Some(length),
+                        reader: reader.boxed(),
+                    }
+                },
+            };
+        }
+
+        let url = req.url.to_string();
+        let headers = req
+            .headers
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
         let body: serde_json::Value =
             serde_json::from_slice(&req.body.unwrap_or_default()).unwrap();
 
+        let bson = *self.sync_stream_bson.lock().unwrap();
         let (send, recv) = async_channel::bounded(1);
         let response = Response {
             status: 200,
             body: ResponseBody {
-                reader: MockSyncLinesResponse { receive: recv }.boxed(),
+                reader: MockSyncLinesResponse {
+                    receive: recv,
+                    bson,
+                }
+                .boxed(),
                 length: None,
             },
-            content_type: Some("application/json".to_string()),
+            content_type: Some(
+                if bson {
+                    "application/vnd.powersync.bson-stream"
+                } else {
+                    "application/json"
+                }
+                .to_string(),
+            ),
         };
 
         self.send_requests
             .send(PendingSyncResponse {
+                url,
+                request_headers: headers,
                 request_data: body,
                 channel: send,
             })
@@ -118,11 +174,20 @@ impl MockSyncService {
 }
 
 pub struct PendingSyncResponse {
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
     pub request_data: serde_json::Value,
     pub channel: async_channel::Sender<SyncLine<'static>>,
 }
 
 impl PendingSyncResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.request_headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
     pub async fn send_checkpoint(&self, checkpoint: Checkpoint<'static>) {
         self.channel
             .send(SyncLine::Checkpoint(checkpoint))
@@ -173,6 +238,7 @@ pin_project! {
     struct MockSyncLinesResponse {
         #[pin]
         receive: async_channel::Receiver<SyncLine<'static>>,
+        bson: bool,
     }
 }
 
@@ -181,10 +247,18 @@ impl Stream for MockSyncLinesResponse {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
+        let bson = *this.bson;
         let line = ready!(this.receive.poll_next(cx)).map(|line| {
             let mut writer = Vec::new();
-            serde_json::to_writer(&mut writer, &line).unwrap();
-            writer.push(b'\n');
+            if bson {
+                let Value::Object(line) = serde_json::to_value(&line).unwrap() else {
+                    panic!("Sync lines must be objects");
+                };
+                write_bson_document(&mut writer, &line);
+            } else {
+                serde_json::to_writer(&mut writer, &line).unwrap();
+                writer.push(b'\n');
+            }
             Ok(Bytes::from(writer))
         });
 
@@ -192,6 +266,76 @@ impl Stream for MockSyncLinesResponse {
     }
 }
 
+/// Encodes `object` as a BSON document, as the sync service would when responding with
+/// `application/vnd.powersync.bson-stream`.
+fn write_bson_document(writer: &mut Vec<u8>, object: &Map<String, Value>) {
+    write_bson_elements(
+        writer,
+        object.iter().map(|(key, value)| (key.clone(), value)),
+    );
+}
+
+fn write_bson_elements<'a>(
+    writer: &mut Vec<u8>,
+    elements: impl Iterator<Item = (String, &'a Value)>,
+) {
+    // The document starts with its total length (including the length itself), which is
+    // patched in once all elements have been written.
+    let start = writer.len();
+    writer.extend_from_slice(&0i32.to_le_bytes());
+
+    for (key, value) in elements {
+        let type_index = writer.len();
+        writer.push(0);
+        writer.extend_from_slice(key.as_bytes());
+        writer.push(0);
+
+        writer[type_index] = match value {
+            Value::Null => 0x0A,
+            Value::Bool(value) => {
+                writer.push(*value as u8);
+                0x08
+            }
+            Value::Number(number) => {
+                if let Some(value) = number.as_i64().and_then(|n| i32::try_from(n).ok()) {
+                    writer.extend_from_slice(&value.to_le_bytes());
+                    0x10
+                } else if let Some(value) = number.as_i64() {
+                    writer.extend_from_slice(&value.to_le_bytes());
+                    0x12
+                } else {
+                    writer.extend_from_slice(&number.as_f64().unwrap().to_le_bytes());
+                    0x01
+                }
+            }
+            Value::String(value) => {
+                writer.extend_from_slice(&(value.len() as i32 + 1).to_le_bytes());
+                writer.extend_from_slice(value.as_bytes());
+                writer.push(0);
+                0x02
+            }
+            Value::Array(items) => {
+                write_bson_elements(
+                    writer,
+                    items
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| (index.to_string(), item)),
+                );
+                0x04
+            }
+            Value::Object(object) => {
+                write_bson_document(writer, object);
+                0x03
+            }
+        };
+    }
+
+    writer.push(0);
+    let length = (writer.len() - start) as i32;
+    writer[start..start + 4].copy_from_slice(&length.to_le_bytes());
+}
+
 #[derive(Serialize)]
 pub struct WriteCheckpointResponse {
     data: WriteCheckpointResponseData,
@@ -220,6 +364,7 @@ impl BackendConnector for TestConnector {
         Ok(PowerSyncCredentials {
             endpoint: "https://rust.unit.test.powersync.com/".to_string(),
             token: "token".to_string(),
+            expires_at: None,
         })
     }
 
@@ -60,7 +60,34 @@ impl<'a> Checkpoint<'a> {
 pub struct StreamDescription<'a> {
     pub name: &'a str,
     pub is_default: bool,
-    pub errors: Vec<()>,
+    pub errors: Vec<StreamSubscriptionError<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct StreamSubscriptionError<'a> {
+    pub subscription: StreamSubscriptionErrorCause,
+    pub message: &'a str,
+}
+
+/// The subscription causing a [StreamSubscriptionError], serialized as `"default"` or as the
+/// index of an explicit subscription in the sync request.
+pub enum StreamSubscriptionErrorCause {
+    Default,
+    ExplicitSubscription(usize),
+}
+
+impl Serialize for StreamSubscriptionErrorCause {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            StreamSubscriptionErrorCause::Default => serializer.serialize_str("default"),
+            StreamSubscriptionErrorCause::ExplicitSubscription(index) => {
+                serializer.serialize_u64(*index as u64)
+            }
+        }
+    }
 }
 
 #[serde_as]